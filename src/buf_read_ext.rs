@@ -1,5 +1,20 @@
+#[cfg(feature = "time")]
+use std::time::Duration;
+
+use bytes::Bytes;
+
 use crate::AsyncBufRead;
-use crate::peek::{Peek, peek};
+use crate::chain::{Chain, chain};
+#[cfg(feature = "http")]
+use crate::http::{
+    HeadLimits, ReadRequestHead, ReadResponseHead, read_request_head, read_response_head,
+};
+use crate::peek::{Peek, PeekExact, PeekUntil, peek, peek_exact, peek_until};
+use crate::read_bytes::{ReadBytes, read_bytes};
+use crate::split::{Lines, Split, lines, split};
+use crate::take::{Take, take};
+#[cfg(feature = "time")]
+use crate::time::{Timeout, timeout};
 
 /// An extension trait which adds utility methods to [`AsyncBufRead`] types.
 ///
@@ -58,6 +73,109 @@ pub trait AsyncBufReadExt: AsyncBufRead {
         peek(self, amt)
     }
 
+    /// Peek into the content of the internal buffer, filling it with more
+    /// data from the inner reader until it holds at least the requested amount.
+    ///
+    /// Unlike [`peek`], which performs at most one read on the inner reader,
+    /// this function keeps reading until exactly `amt` bytes can be returned.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn peek_exact(&mut self, amt: usize) -> io::Result<&[u8]>;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an I/O error if the underlying reader was
+    /// read, but returned an error.
+    ///
+    /// If the inner reader reaches EOF before `amt` bytes are available, an
+    /// error of kind [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) is
+    /// returned. It wraps an [`UnexpectedEof`] carrying the partial length.
+    /// If the buffer stops growing before EOF, for example in passthrough
+    /// mode, an error of kind [`Other`](std::io::ErrorKind::Other) is returned.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If you use it as the event in a
+    /// `select!` statement and some other branch completes first,
+    /// then it is guaranteed that no data was read.
+    ///
+    /// [`peek`]: crate::AsyncBufReadExt::peek
+    /// [`UnexpectedEof`]: crate::UnexpectedEof
+    fn peek_exact(&mut self, amt: usize) -> PeekExact<'_, Self>
+    where
+        Self: Unpin,
+    {
+        peek_exact(self, amt)
+    }
+
+    /// Peek into the content of the internal buffer up to and including the
+    /// `delimiter`, filling it with more data from the inner reader until the
+    /// delimiter is found.
+    ///
+    /// The delimiter can be a single byte (`[b'\n']`) or a pattern (`b"\r\n"`).
+    /// The buffer is filled incrementally and the bytes already scanned are not
+    /// scanned again. Like [`peek`], this function doesn't consume the data.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn peek_until(&mut self, delimiter: impl AsRef<[u8]>, limit: usize) -> io::Result<&[u8]>;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an I/O error if the underlying reader was
+    /// read, but returned an error.
+    ///
+    /// If the delimiter is not found within the first `limit` bytes, an error of
+    /// kind [`InvalidData`](std::io::ErrorKind::InvalidData) wrapping a
+    /// [`LimitExceeded`] is returned. If the inner reader reaches EOF before the
+    /// delimiter, an error of kind [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof)
+    /// wrapping an [`UnexpectedEof`] is returned. If the buffer stops growing
+    /// before EOF, for example in passthrough mode, an error of kind
+    /// [`Other`](std::io::ErrorKind::Other) is returned. In all cases, the data
+    /// stays in the buffer.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If you use it as the event in a
+    /// `select!` statement and some other branch completes first,
+    /// then it is guaranteed that no data was read.
+    ///
+    /// [`peek`]: crate::AsyncBufReadExt::peek
+    /// [`LimitExceeded`]: crate::LimitExceeded
+    /// [`UnexpectedEof`]: crate::UnexpectedEof
+    fn peek_until<D>(&mut self, delimiter: D, limit: usize) -> PeekUntil<'_, Self, D>
+    where
+        Self: Unpin,
+        D: AsRef<[u8]>,
+    {
+        peek_until(self, delimiter, limit)
+    }
+
+    /// Peek into the content of the internal buffer up to and including the
+    /// next `\n`, filling it with more data from the inner reader until it is found.
+    ///
+    /// The line ending is returned as is, including the `\r` of a `\r\n`.
+    /// See [`peek_until`] for the errors.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn peek_line(&mut self, limit: usize) -> io::Result<&[u8]>;
+    /// ```
+    ///
+    /// [`peek_until`]: crate::AsyncBufReadExt::peek_until
+    fn peek_line(&mut self, limit: usize) -> PeekUntil<'_, Self, &'static [u8]>
+    where
+        Self: Unpin,
+    {
+        peek_until(self, b"\n", limit)
+    }
+
     /// Tells this buffer that `amt` bytes have been consumed from the
     /// buffer, so they should no longer be returned in calls to read.
     ///
@@ -71,6 +189,300 @@ pub trait AsyncBufReadExt: AsyncBufRead {
     {
         std::pin::Pin::new(self).consume(amt);
     }
+
+    /// Reads exactly `amt` bytes, returning them as an owned [`Bytes`].
+    ///
+    /// The buffer is filled like with [`peek_exact`], then the data is split
+    /// from the internal buffer. For an [`AsyncBufReader`], this doesn't copy
+    /// the data, which makes it suitable to hand off frames to other tasks.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn read_bytes(&mut self, amt: usize) -> io::Result<Bytes>;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`peek_exact`], if an error is returned no data was consumed.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If you use it as the event in a
+    /// `select!` statement and some other branch completes first,
+    /// then it is guaranteed that no data was read.
+    ///
+    /// [`peek_exact`]: crate::AsyncBufReadExt::peek_exact
+    /// [`AsyncBufReader`]: crate::AsyncBufReader
+    fn read_bytes(&mut self, amt: usize) -> ReadBytes<'_, Self>
+    where
+        Self: Unpin,
+    {
+        read_bytes(self, amt)
+    }
+
+    /// Returns a stream over the lines of the reader.
+    ///
+    /// Each line is split from the internal buffer as an owned [`Bytes`],
+    /// without its line ending. For an [`AsyncBufReader`], this doesn't copy
+    /// the data. The last line is returned even if it has no line ending.
+    ///
+    /// Lines end with `\n` or `\r\n` by default, use
+    /// [`with_line_ending`](Lines::with_line_ending) to only accept one of them.
+    ///
+    /// # Errors
+    ///
+    /// The stream yields an I/O error if the underlying reader was read, but
+    /// returned an error.
+    ///
+    /// If a line, including its line ending, is longer than `limit`, an error
+    /// of kind [`InvalidData`](std::io::ErrorKind::InvalidData) wrapping a
+    /// [`LimitExceeded`] is yielded. The data stays in the buffer.
+    ///
+    /// # Cancel safety
+    ///
+    /// The stream is cancel safe. If you use `next()` as the event in a
+    /// `select!` statement and some other branch completes first,
+    /// then it is guaranteed that no data was read.
+    ///
+    /// [`AsyncBufReader`]: crate::AsyncBufReader
+    /// [`LimitExceeded`]: crate::LimitExceeded
+    fn lines(&mut self, limit: usize) -> Lines<'_, Self>
+    where
+        Self: Unpin,
+    {
+        lines(self, limit)
+    }
+
+    /// Returns a stream over the segments of the reader separated by `delimiter`.
+    ///
+    /// Each segment is split from the internal buffer as an owned [`Bytes`],
+    /// without the delimiter. See [`lines`] for the errors and cancel safety.
+    ///
+    /// [`lines`]: crate::AsyncBufReadExt::lines
+    fn split(&mut self, delimiter: u8, limit: usize) -> Split<'_, Self>
+    where
+        Self: Unpin,
+    {
+        split(self, delimiter, limit)
+    }
+
+    /// Reads the head of an HTTP/1.x request, up to and including the empty
+    /// line ending it.
+    ///
+    /// The buffer is filled until it contains the empty line ending the head,
+    /// which must end with `\r\n\r\n`. The head is then parsed with
+    /// [`httparse`] and split from the internal buffer. The parts of the returned
+    /// [`RequestHead`] are slices of it, for an [`AsyncBufReader`] this
+    /// doesn't copy the data. The body stays in the buffer.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn read_request_head(&mut self, limits: HeadLimits) -> io::Result<RequestHead>;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an I/O error if the underlying reader was
+    /// read, but returned an error.
+    ///
+    /// If the head is larger than `limits.max_size` or has more than
+    /// `limits.max_headers` headers, an error of kind
+    /// [`InvalidData`](std::io::ErrorKind::InvalidData) wrapping a
+    /// [`LimitExceeded`] is returned. If the head is malformed, an error of
+    /// the same kind wrapping a [`ParseError`] is returned. If the inner
+    /// reader reaches EOF before the end of the head, an error wrapping an
+    /// [`UnexpectedEof`] is returned. In all cases, no data was consumed.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If you use it as the event in a
+    /// `select!` statement and some other branch completes first,
+    /// then it is guaranteed that no data was read.
+    ///
+    /// [`RequestHead`]: crate::http::RequestHead
+    /// [`ParseError`]: crate::http::ParseError
+    /// [`AsyncBufReader`]: crate::AsyncBufReader
+    /// [`LimitExceeded`]: crate::LimitExceeded
+    /// [`UnexpectedEof`]: crate::UnexpectedEof
+    #[cfg(feature = "http")]
+    fn read_request_head(&mut self, limits: HeadLimits) -> ReadRequestHead<'_, Self>
+    where
+        Self: Unpin,
+    {
+        read_request_head(self, limits)
+    }
+
+    /// Reads the head of an HTTP/1.x response, up to and including the empty
+    /// line ending it.
+    ///
+    /// See [`read_request_head`] for the details, errors and cancel safety.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn read_response_head(&mut self, limits: HeadLimits) -> io::Result<ResponseHead>;
+    /// ```
+    ///
+    /// [`read_request_head`]: crate::AsyncBufReadExt::read_request_head
+    #[cfg(feature = "http")]
+    fn read_response_head(&mut self, limits: HeadLimits) -> ReadResponseHead<'_, Self>
+    where
+        Self: Unpin,
+    {
+        read_response_head(self, limits)
+    }
+
+    /// Like [`peek`], but fails if no data could be returned within `duration`.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn peek_timeout(&mut self, amt: usize, duration: Duration) -> io::Result<&[u8]>;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`peek`]. If `duration` elapses first, an error of kind
+    /// [`TimedOut`](std::io::ErrorKind::TimedOut) wrapping a [`TimedOut`] is
+    /// returned and the data stays in the buffer.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if called outside of a `tokio` runtime with the
+    /// timer enabled.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If you use it as the event in a
+    /// `select!` statement and some other branch completes first,
+    /// then it is guaranteed that no data was read.
+    ///
+    /// [`peek`]: crate::AsyncBufReadExt::peek
+    /// [`TimedOut`]: crate::time::TimedOut
+    #[cfg(feature = "time")]
+    fn peek_timeout(&mut self, amt: usize, duration: Duration) -> Timeout<Peek<'_, Self>>
+    where
+        Self: Unpin,
+    {
+        timeout(peek(self, amt), duration)
+    }
+
+    /// Like [`peek_exact`], but fails if `amt` bytes are not available within
+    /// `duration`.
+    ///
+    /// See [`peek_timeout`] for the timeout.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn peek_exact_timeout(&mut self, amt: usize, duration: Duration) -> io::Result<&[u8]>;
+    /// ```
+    ///
+    /// [`peek_exact`]: crate::AsyncBufReadExt::peek_exact
+    /// [`peek_timeout`]: crate::AsyncBufReadExt::peek_timeout
+    #[cfg(feature = "time")]
+    fn peek_exact_timeout(&mut self, amt: usize, duration: Duration) -> Timeout<PeekExact<'_, Self>>
+    where
+        Self: Unpin,
+    {
+        timeout(peek_exact(self, amt), duration)
+    }
+
+    /// Like [`peek_until`], but fails if the delimiter is not found within
+    /// `duration`.
+    ///
+    /// See [`peek_timeout`] for the timeout.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn peek_until_timeout(&mut self, delimiter: impl AsRef<[u8]>, limit: usize, duration: Duration) -> io::Result<&[u8]>;
+    /// ```
+    ///
+    /// [`peek_until`]: crate::AsyncBufReadExt::peek_until
+    /// [`peek_timeout`]: crate::AsyncBufReadExt::peek_timeout
+    #[cfg(feature = "time")]
+    fn peek_until_timeout<D>(
+        &mut self,
+        delimiter: D,
+        limit: usize,
+        duration: Duration,
+    ) -> Timeout<PeekUntil<'_, Self, D>>
+    where
+        Self: Unpin,
+        D: AsRef<[u8]>,
+    {
+        timeout(peek_until(self, delimiter, limit), duration)
+    }
+
+    /// Like [`read_bytes`], but fails if `amt` bytes are not available within
+    /// `duration`.
+    ///
+    /// See [`peek_timeout`] for the timeout, no data is consumed when it elapses.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn read_bytes_timeout(&mut self, amt: usize, duration: Duration) -> io::Result<Bytes>;
+    /// ```
+    ///
+    /// [`read_bytes`]: crate::AsyncBufReadExt::read_bytes
+    /// [`peek_timeout`]: crate::AsyncBufReadExt::peek_timeout
+    #[cfg(feature = "time")]
+    fn read_bytes_timeout(&mut self, amt: usize, duration: Duration) -> Timeout<ReadBytes<'_, Self>>
+    where
+        Self: Unpin,
+    {
+        timeout(read_bytes(self, amt), duration)
+    }
+
+    /// Returns a reader limited to the next `limit` bytes of this reader.
+    ///
+    /// The returned [`Take`] reports EOF once `limit` bytes are buffered, so
+    /// requests are clamped to the limit and [`peek_exact`] fails past it.
+    /// The data after the limit stays in this reader, which can be used again
+    /// once the `Take` is dropped.
+    ///
+    /// This is useful to read a body with a known `Content-Length` or a
+    /// record with a length prefix.
+    ///
+    /// [`peek_exact`]: crate::AsyncBufReadExt::peek_exact
+    fn take(&mut self, limit: u64) -> Take<&mut Self>
+    where
+        Self: Unpin,
+    {
+        take(self, limit)
+    }
+
+    /// Creates a reader that reads all the data of this reader, then the data
+    /// of `next`.
+    ///
+    /// The returned [`Chain`] only reports EOF once `next` does. Requests that
+    /// span both readers are satisfied by copying the end of this reader and
+    /// the start of `next` to an internal buffer. This is useful to parse a
+    /// rewritten prefix followed by the rest of a stream.
+    fn chain<B>(self, next: B) -> Chain<Self, B>
+    where
+        Self: Sized,
+        B: AsyncBufRead,
+    {
+        chain(self, next)
+    }
+
+    /// Takes all the data currently in the internal buffer, returning it as
+    /// an owned [`Bytes`].
+    ///
+    /// This doesn't read from the inner reader.
+    fn take_buffered(&mut self) -> Bytes
+    where
+        Self: Unpin,
+    {
+        let reader = std::pin::Pin::new(self);
+        let amt = reader.as_ref().buf().len();
+        reader.split_to(amt)
+    }
 }
 
 impl<R: AsyncBufRead + ?Sized> AsyncBufReadExt for R {}
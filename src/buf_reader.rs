@@ -1,14 +1,15 @@
 use std::{
     io::{self, IoSlice},
     pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
     task::{Context, Poll, ready},
 };
 
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use pin_project_lite::pin_project;
 
-use crate::io::{AsyncRead, AsyncWrite, ReadBuf};
-use crate::{AsyncBufPassthrough, AsyncBufRead};
+use crate::io::{self as rt, AsyncRead, AsyncWrite, ReadBuf};
+use crate::{AsyncBufPassthrough, AsyncBufRead, BufferOverflow, Prefixed};
 
 pin_project! {
     /// The `AsyncBufReader` struct adds buffering to any reader.
@@ -16,21 +17,83 @@ pin_project! {
     /// This allows for both efficient reading of small amounts of data and
     /// peeking for parsing were you will want to read the data multiple times.
     /// The internal buffer of `AsyncBufReader` will expand based on the requested
-    /// amount of data.
+    /// amount of data, up to an optional maximum buffer size.
     ///
     /// When the `AsyncBufReader` is dropped, the contents of its buffer will be
     /// discarded. Creating multiple instances of a `AsyncBufReader` on the same
-    /// stream can cause data loss.
+    /// stream can cause data loss. Use [`into_parts`](AsyncBufReader::into_parts)
+    /// to retrieve the buffered data along with the underlying reader.
     pub struct AsyncBufReader<R> {
         #[pin]
         reader: R,
         passthrough: bool,
         buf: BytesMut,
+        // Amount of consumed data retained at the start of the buffer, for
+        // checkpoints or until the next read from the underlying reader
+        pos: usize,
+        // Position in the stream of the start of the buffer
+        start: u64,
+        // Id and position in the stream of the active checkpoints
+        checkpoints: Vec<(u64, u64)>,
         chunk_size: usize,
+        max_buffer_size: Option<usize>,
         eof: bool,
     }
 }
 
+/// A marker of a position in the stream read by an [`AsyncBufReader`].
+///
+/// It is created by [`AsyncBufReader::checkpoint`] and must be released with
+/// either [`AsyncBufReader::rewind`] or [`AsyncBufReader::commit`]. As long as
+/// it is alive, the data consumed after it is kept in the buffer.
+#[derive(Debug)]
+#[must_use = "checkpoints must be rewound or committed to release the buffered data"]
+pub struct Checkpoint {
+    // Unique among all readers, so that a foreign checkpoint is rejected
+    id: u64,
+    position: u64,
+}
+
+static NEXT_CHECKPOINT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The parts of an [`AsyncBufReader`], returned by [`AsyncBufReader::into_parts`].
+///
+/// It holds the underlying reader, the data that was buffered but not yet
+/// consumed and the configuration of the reader, so that the reader can be
+/// rebuilt with [`AsyncBufReader::from_parts`] without losing any data.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ReaderParts<R> {
+    /// The underlying reader.
+    pub reader: R,
+    /// The data that was buffered but not yet consumed.
+    pub buffer: BytesMut,
+    /// The chunk size of the reader.
+    pub chunk_size: usize,
+    /// The maximum size of the internal buffer, if any.
+    pub max_buffer_size: Option<usize>,
+    /// True if the reader was in passthrough mode.
+    pub passthrough: bool,
+    /// True if the underlying reader has reached EOF.
+    pub eof: bool,
+}
+
+impl<R> ReaderParts<R> {
+    /// Creates new parts from a reader and the data already read from it.
+    ///
+    /// The other fields are set to the defaults of [`AsyncBufReader`].
+    pub fn new(reader: R, buffer: BytesMut, chunk_size: usize) -> Self {
+        Self {
+            reader,
+            buffer,
+            chunk_size,
+            max_buffer_size: None,
+            passthrough: false,
+            eof: false,
+        }
+    }
+}
+
 const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
 
 impl<R: AsyncRead> AsyncBufReader<R> {
@@ -47,24 +110,82 @@ impl<R: AsyncRead> AsyncBufReader<R> {
             reader,
             buf: BytesMut::with_capacity(chunk_size),
             passthrough: false,
+            pos: 0,
+            start: 0,
+            checkpoints: Vec::new(),
             chunk_size,
+            max_buffer_size: None,
             eof: false,
         }
     }
 
+    /// Creates a new `AsyncBufReader` with the default chunk size, which will
+    /// return `prefix` before any data from the reader.
+    ///
+    /// This is useful when some data was already read from the stream before
+    /// the `AsyncBufReader` was created, for example in an accept loop.
+    pub fn with_prefix(prefix: &[u8], reader: R) -> Self {
+        let mut buffer = BytesMut::with_capacity(std::cmp::max(prefix.len(), DEFAULT_CHUNK_SIZE));
+        buffer.extend_from_slice(prefix);
+        Self::from_parts(ReaderParts::new(reader, buffer, DEFAULT_CHUNK_SIZE))
+    }
+
+    /// Creates a new `AsyncBufReader` from its parts.
+    ///
+    /// The buffered data of the parts will be returned before any data from
+    /// the underlying reader.
+    pub fn from_parts(parts: ReaderParts<R>) -> Self {
+        Self {
+            reader: parts.reader,
+            buf: parts.buffer,
+            passthrough: parts.passthrough,
+            pos: 0,
+            start: 0,
+            checkpoints: Vec::new(),
+            chunk_size: parts.chunk_size,
+            max_buffer_size: parts.max_buffer_size,
+            eof: parts.eof,
+        }
+    }
+
+    /// Sets the maximum size of the internal buffer.
+    ///
+    /// The limit covers the unconsumed data and the consumed data retained for
+    /// checkpoints. Any request that would grow the buffer past the limit will
+    /// fail with an error of kind [`OutOfMemory`](io::ErrorKind::OutOfMemory)
+    /// wrapping a [`BufferOverflow`] instead of growing the buffer.
+    ///
+    /// The initial allocation is shrunk to the limit. Data that is already
+    /// buffered, like a prefix, is kept even if it is larger than the limit,
+    /// but no more data is read until enough of it is consumed.
+    pub fn with_max_buffer_size(mut self, max_buffer_size: usize) -> Self {
+        if self.buf.capacity() > max_buffer_size {
+            let mut buf = BytesMut::with_capacity(std::cmp::max(self.buf.len(), max_buffer_size));
+            buf.extend_from_slice(&self.buf);
+            self.buf = buf;
+        }
+        self.max_buffer_size = Some(max_buffer_size);
+        self
+    }
+
+    /// Returns the maximum size of the internal buffer, if any.
+    pub fn max_buffer_size(&self) -> Option<usize> {
+        self.max_buffer_size
+    }
+
     /// Returns the current capacity of the internal buffer.
     pub fn capacity(&self) -> usize {
-        self.buf.capacity()
+        self.buf.capacity() - self.pos
     }
 
     /// Returns the current length of the internal buffer.
     pub fn len(&self) -> usize {
-        self.buf.len()
+        self.buf.len() - self.pos
     }
 
     /// Returns true if the internal buffer is empty.
     pub fn is_empty(&self) -> bool {
-        self.buf.is_empty()
+        self.len() == 0
     }
 
     /// Gets a reference to the underlying reader.
@@ -74,6 +195,13 @@ impl<R: AsyncRead> AsyncBufReader<R> {
         &self.reader
     }
 
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
     /// Gets a pinned mutable reference to the underlying reader.
     ///
     /// It is inadvisable to directly read from the underlying reader.
@@ -81,17 +209,265 @@ impl<R: AsyncRead> AsyncBufReader<R> {
         self.project().reader
     }
 
+    /// Consumes this `AsyncBufReader`, returning the underlying reader.
+    ///
+    /// Note that any leftover data in the internal buffer is lost, use
+    /// [`into_parts`](AsyncBufReader::into_parts) to keep it.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Consumes this `AsyncBufReader`, returning the underlying reader along
+    /// with the data that was buffered but not yet consumed.
+    ///
+    /// This is useful to hand the stream off to another protocol handler,
+    /// for example after an HTTP upgrade, without losing the data that was
+    /// read ahead. The passthrough and EOF states are part of the returned
+    /// [`ReaderParts`], active checkpoints are released.
+    pub fn into_parts(mut self) -> ReaderParts<R> {
+        ReaderParts {
+            buffer: self.buf.split_off(self.pos),
+            reader: self.reader,
+            chunk_size: self.chunk_size,
+            max_buffer_size: self.max_buffer_size,
+            passthrough: self.passthrough,
+            eof: self.eof,
+        }
+    }
+
+    /// Consumes this `AsyncBufReader`, returning the underlying reader
+    /// prefixed with the data that was buffered but not yet consumed.
+    ///
+    /// Reading from the returned [`Prefixed`] yields exactly the data that
+    /// would have been read from this reader. This is useful to wrap the
+    /// stream in an asynchronous layer, like a TLS acceptor, after sniffing it.
+    pub fn into_prefixed(self) -> Prefixed<R> {
+        let parts = self.into_parts();
+        Prefixed::new(parts.buffer, parts.reader)
+    }
+
+    /// Transforms the underlying reader into a new one, feeding it the data
+    /// that was buffered but not yet consumed first.
+    ///
+    /// The new reader is created by `f` from a [`Prefixed`] reader, so no
+    /// buffered data is lost. For example, a `TcpStream` can be turned into a
+    /// `TlsStream<Prefixed<TcpStream>>` after detecting a ClientHello.
+    ///
+    /// The returned `AsyncBufReader` keeps the configuration of this one, but
+    /// starts with an empty buffer. Active checkpoints are released.
+    pub fn upgrade<S, F>(self, f: F) -> AsyncBufReader<S>
+    where
+        S: AsyncRead,
+        F: FnOnce(Prefixed<R>) -> S,
+    {
+        let parts = self.into_parts();
+        let reader = f(Prefixed::new(parts.buffer, parts.reader));
+        AsyncBufReader::from_parts(ReaderParts {
+            reader,
+            buffer: BytesMut::with_capacity(parts.chunk_size),
+            chunk_size: parts.chunk_size,
+            max_buffer_size: parts.max_buffer_size,
+            passthrough: parts.passthrough,
+            eof: false,
+        })
+    }
+
+    /// Transforms the underlying reader into a new one, keeping the buffer.
+    ///
+    /// Unlike [`upgrade`](AsyncBufReader::upgrade), the buffered data stays in
+    /// this reader and will be returned before any data of the new reader.
+    /// This is suited to wrappers that don't transform the data.
+    pub fn map_inner<S, F>(self, f: F) -> AsyncBufReader<S>
+    where
+        S: AsyncRead,
+        F: FnOnce(R) -> S,
+    {
+        AsyncBufReader {
+            reader: f(self.reader),
+            passthrough: self.passthrough,
+            buf: self.buf,
+            pos: self.pos,
+            start: self.start,
+            checkpoints: self.checkpoints,
+            chunk_size: self.chunk_size,
+            max_buffer_size: self.max_buffer_size,
+            eof: self.eof,
+        }
+    }
+
     /// Returns a reference to the internally buffered data.
     pub fn buffer(&self) -> &[u8] {
-        self.buf.as_ref()
+        &self.buf[self.pos..]
+    }
+
+    /// Puts `data` back in front of the internal buffer.
+    ///
+    /// The data will be returned first by subsequent reads, including reads
+    /// in passthrough mode. This is useful when a parser decides that some
+    /// of the data it consumed belongs to the next message.
+    ///
+    /// This always copies the buffered data, prefer [`unconsume`] when the
+    /// data was just consumed from this reader.
+    ///
+    /// # Errors
+    ///
+    /// If the buffer would grow past the maximum buffer size, a
+    /// [`BufferOverflow`] is returned and the buffer is left unchanged.
+    ///
+    /// [`unconsume`]: AsyncBufReader::unconsume
+    pub fn unread(&mut self, data: &[u8]) -> Result<(), BufferOverflow> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let len = self.buf.len() + data.len();
+        let max = self.max_buffer_size.unwrap_or(usize::MAX);
+        if len > max {
+            return Err(BufferOverflow {
+                requested: len,
+                max_buffer_size: max,
+            });
+        }
+        let mut buf =
+            BytesMut::with_capacity(std::cmp::min(std::cmp::max(len, self.chunk_size), max));
+        buf.extend_from_slice(&self.buf[..self.pos]);
+        buf.extend_from_slice(data);
+        buf.extend_from_slice(&self.buf[self.pos..]);
+        self.buf = buf;
+        Ok(())
+    }
+
+    /// Puts the last `amt` consumed bytes back in front of the internal buffer.
+    ///
+    /// Consumed data is retained until the next read from the underlying
+    /// reader, or as long as a [`Checkpoint`] covers it. Use [`retained`] to
+    /// know how much data can be unconsumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `amt` is greater than the retained data.
+    ///
+    /// [`retained`]: AsyncBufReader::retained
+    pub fn unconsume(&mut self, amt: usize) {
+        assert!(
+            amt <= self.pos,
+            "cannot unconsume more than the retained data"
+        );
+        self.pos -= amt;
+    }
+
+    /// Returns the amount of consumed data that can still be unconsumed.
+    pub fn retained(&self) -> usize {
+        self.pos
+    }
+
+    /// Creates a checkpoint at the current position in the stream.
+    ///
+    /// While the checkpoint is alive, consumed data is kept in the buffer
+    /// so that the reader can be rewound to the checkpoint with [`rewind`].
+    /// This is useful to try multiple parsers on the same data without
+    /// copying it into a side buffer.
+    ///
+    /// Checkpoints only cover the data that goes through the buffer, data read
+    /// directly from the underlying reader in passthrough mode can't be rewound.
+    ///
+    /// [`rewind`]: AsyncBufReader::rewind
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        let id = NEXT_CHECKPOINT_ID.fetch_add(1, Ordering::Relaxed);
+        let position = self.start + self.pos as u64;
+        self.checkpoints.push((id, position));
+        Checkpoint { id, position }
+    }
+
+    /// Rewinds the reader to the given checkpoint and releases it.
+    ///
+    /// The data consumed since the checkpoint will be returned again by
+    /// subsequent reads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint was not created by this reader.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        let idx = self.checkpoint_index(&checkpoint);
+        // The data is retained from the oldest checkpoint, so this can't underflow
+        self.pos = (checkpoint.position - self.start) as usize;
+        self.release(idx);
+    }
+
+    /// Releases the checkpoint, keeping the current position.
+    ///
+    /// Once no checkpoint is alive, the consumed data is dropped from the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint was not created by this reader.
+    pub fn commit(&mut self, checkpoint: Checkpoint) {
+        let idx = self.checkpoint_index(&checkpoint);
+        self.release(idx);
+    }
+
+    fn checkpoint_index(&self, checkpoint: &Checkpoint) -> usize {
+        self.checkpoints
+            .iter()
+            .position(|(id, _)| *id == checkpoint.id)
+            .expect("checkpoint was not created by this reader")
+    }
+
+    fn release(&mut self, idx: usize) {
+        self.checkpoints.swap_remove(idx);
+
+        // Drop the data that is no longer covered by any checkpoint
+        let retain_from = self
+            .checkpoints
+            .iter()
+            .map(|(_, position)| *position)
+            .min()
+            .unwrap_or(self.start + self.pos as u64);
+        let amt = (retain_from - self.start) as usize;
+        self.buf.advance(amt);
+        self.start += amt as u64;
+        self.pos -= amt;
+    }
+
+    /// Drops the consumed data retained at the start of the buffer, unless a
+    /// checkpoint needs it.
+    #[inline]
+    fn drop_consumed(self: Pin<&mut Self>) {
+        let me = self.project();
+        if !me.checkpoints.is_empty() || *me.pos == 0 {
+            return;
+        }
+        me.buf.advance(*me.pos);
+        *me.start += *me.pos as u64;
+        *me.pos = 0;
+    }
+
+    /// Returns the unconsumed data as a mutable buffer.
+    ///
+    /// The data removed from the front of the returned buffer is consumed.
+    ///
+    /// Fails if a checkpoint is active, since the consumed data can't be retained.
+    #[cfg(feature = "codec")]
+    pub(crate) fn buffer_mut(mut self: Pin<&mut Self>) -> io::Result<&mut BytesMut> {
+        if !self.checkpoints.is_empty() {
+            return Err(io::Error::other(
+                "cannot decode frames while a checkpoint is active",
+            ));
+        }
+        self.as_mut().drop_consumed();
+        Ok(self.project().buf)
     }
 
     /// Invalidates all data in the internal buffer.
     #[inline]
     fn discard_buffer(self: Pin<&mut Self>) {
         let me = self.project();
+        if !me.checkpoints.is_empty() {
+            return;
+        }
         // Force drop the buffer to ensure the memory is freed
+        *me.start += me.buf.len() as u64;
         *me.buf = BytesMut::new();
+        *me.pos = 0;
     }
 }
 
@@ -101,20 +477,67 @@ impl<R> AsyncBufPassthrough for AsyncBufReader<R> {
     }
 }
 
-impl<R: AsyncRead> AsyncRead for AsyncBufReader<R> {
-    fn poll_read(
+impl<R: AsyncRead> AsyncBufReader<R> {
+    /// Reads once from the inner reader into the buffer, making room for
+    /// `amt` bytes.
+    fn poll_read_more(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<()>> {
+        let max = self.max_buffer_size.unwrap_or(usize::MAX);
+        self.as_mut().drop_consumed();
+        let me = self.project();
+        let pos = *me.pos;
+
+        // The data retained for checkpoints counts against the limit too
+        let room = max.saturating_sub(me.buf.len());
+        if pos.saturating_add(amt) > max {
+            return Poll::Ready(Err(BufferOverflow {
+                requested: pos.saturating_add(amt),
+                max_buffer_size: max,
+            }
+            .into()));
+        }
+
+        // Check if we have enough space in the buffer
+        let len = me.buf.len() - pos;
+        if me.buf.capacity() - pos < amt {
+            let additional = std::cmp::max(*me.chunk_size, amt - len);
+            me.buf.reserve(std::cmp::min(additional, room));
+        }
+
+        // Never read more than what the maximum buffer size allows
+        let spare = me.buf.spare_capacity_mut();
+        let limit = std::cmp::min(spare.len(), room);
+        let mut buf = ReadBuf::uninit(&mut spare[..limit]);
+        ready!(rt::poll_read_buf(me.reader, cx, &mut buf))?;
+        let n = buf.filled().len();
+        if n == 0 {
+            *me.eof = true;
+        }
+        unsafe {
+            // SAFETY: We know that filled will be at maximum the spared capacity and
+            // won't exceed the buffer's capacity
+            me.buf.advance_mut(n);
+        };
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_read_buf(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
         // In passthrough mode or if the requested amount of data is greater than the chunk size,
         // empty the buffer then pass through the read to the underlying reader.
-        if self.passthrough || buf.remaining() >= self.chunk_size {
-            if !self.buf.is_empty() {
-                let amt = std::cmp::min(buf.remaining(), self.buf.len());
-                buf.put_slice(&self.buf[..amt]);
+        // Checkpoints need the data to go through the buffer unless we are in passthrough mode.
+        if self.passthrough || (buf.remaining() >= self.chunk_size && self.checkpoints.is_empty()) {
+            if !self.is_empty() {
+                let amt = std::cmp::min(buf.remaining(), self.len());
+                buf.put_slice(&self.buffer()[..amt]);
                 self.as_mut().consume(amt);
-                if self.buf.is_empty() && self.passthrough {
+                if self.is_empty() && self.passthrough {
                     self.as_mut().discard_buffer();
                 }
                 // Always return if we had some data in the buffer because
@@ -125,9 +548,16 @@ impl<R: AsyncRead> AsyncRead for AsyncBufReader<R> {
             if self.eof {
                 return Poll::Ready(Ok(()));
             }
-            return self.get_pin_mut().poll_read(cx, buf);
+            // The retained data can't be unconsumed once data bypassed the buffer
+            self.as_mut().drop_consumed();
+            return rt::poll_read_buf(self.get_pin_mut(), cx, buf);
         }
-        let rem = ready!(self.as_mut().poll_fill_buf(cx, buf.remaining()))?;
+        let amt = match self.max_buffer_size {
+            // Leave room for the retained data, a full buffer fails the fill
+            Some(max) => std::cmp::min(buf.remaining(), max.saturating_sub(self.pos).max(1)),
+            None => buf.remaining(),
+        };
+        let rem = ready!(self.as_mut().poll_fill_buf(cx, amt))?;
         let amt = std::cmp::min(rem.len(), buf.remaining());
         buf.put_slice(&rem[..amt]);
         self.consume(amt);
@@ -135,6 +565,8 @@ impl<R: AsyncRead> AsyncRead for AsyncBufReader<R> {
     }
 }
 
+rt::impl_async_read!(impl<R> for AsyncBufReader<R> where R: AsyncRead);
+
 impl<R: AsyncRead> AsyncBufRead for AsyncBufReader<R> {
     fn eof(self: Pin<&Self>) -> bool {
         self.get_ref().eof
@@ -145,52 +577,82 @@ impl<R: AsyncRead> AsyncBufRead for AsyncBufReader<R> {
     }
 
     fn poll_fill_buf<'a>(
-        self: Pin<&'a mut Self>,
+        mut self: Pin<&'a mut Self>,
         cx: &mut Context<'_>,
         amt: usize,
     ) -> Poll<io::Result<&'a [u8]>> {
-        let me = self.project();
+        // Refuse requests that could never be satisfied without growing past the limit.
+        let max = self.max_buffer_size.unwrap_or(usize::MAX);
+        if amt > max {
+            return Poll::Ready(Err(BufferOverflow {
+                requested: amt,
+                max_buffer_size: max,
+            }
+            .into()));
+        }
 
         // If we are in passthrough mode or at EOF, return the buffer.
         // Don't attempt to fill the buffer with more data.
-        if *me.passthrough || *me.eof {
-            let rem = std::cmp::min(amt, me.buf.len());
-            return Poll::Ready(Ok(&me.buf[..rem]));
+        // If the buffer has enough data, return it.
+        if self.passthrough || self.eof || self.len() >= amt {
+            let me = self.project();
+            let pos = *me.pos;
+            let rem = std::cmp::min(amt, me.buf.len() - pos);
+            return Poll::Ready(Ok(&me.buf[pos..pos + rem]));
         }
 
-        // If the buffer has enough data, return it
-        if me.buf.len() >= amt {
-            return Poll::Ready(Ok(&me.buf[..amt]));
+        ready!(self.as_mut().poll_read_more(cx, amt))?;
+        let me = self.project();
+        let pos = *me.pos;
+        let rem = std::cmp::min(amt, me.buf.len() - pos);
+        Poll::Ready(Ok(&me.buf[pos..pos + rem]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        // The consumed data is retained until the next fill so that it can be unconsumed
+        let me = self.project();
+        assert!(
+            amt <= me.buf.len() - *me.pos,
+            "cannot consume more than the buffered data"
+        );
+        *me.pos += amt;
+    }
+
+    fn split_to(mut self: Pin<&mut Self>, amt: usize) -> Bytes {
+        self.as_mut().drop_consumed();
+        let me = self.project();
+        if me.checkpoints.is_empty() {
+            *me.start += amt as u64;
+            me.buf.split_to(amt).freeze()
         } else {
-            // Check if we have enough space in the buffer
-            if me.buf.capacity() < amt {
-                me.buf
-                    .reserve(std::cmp::max(*me.chunk_size, amt - me.buf.len()));
-            }
+            // The data must stay in the buffer for the checkpoints
+            let pos = *me.pos;
+            let bytes = Bytes::copy_from_slice(&me.buf[pos..pos + amt]);
+            *me.pos += amt;
+            bytes
         }
+    }
+}
 
-        let mut buf = ReadBuf::uninit(me.buf.spare_capacity_mut());
-        ready!(me.reader.poll_read(cx, &mut buf))?;
-        let n = buf.filled().len();
-        if n == 0 {
-            *me.eof = true;
+/// The runtime `AsyncBufRead` trait, so the reader can be used with the
+/// `read_line` and `lines` methods of the runtime and other libraries.
+///
+/// An empty buffer means EOF for this trait, so the buffer is filled even in
+/// passthrough mode.
+impl<R: AsyncRead> rt::AsyncBufRead for AsyncBufReader<R> {
+    fn poll_fill_buf(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        if self.is_empty() && !self.eof {
+            ready!(self.as_mut().poll_read_more(cx, 1))?;
         }
-        unsafe {
-            // SAFETY: We know that filled will be at maximum the spared capacity and
-            // won't exceed the buffer's capacity
-            me.buf.advance_mut(n);
-        };
-
-        let rem = std::cmp::min(amt, me.buf.len());
-        Poll::Ready(Ok(&me.buf[..rem]))
+        Poll::Ready(Ok(self.into_ref().get_ref().buffer()))
     }
 
     fn consume(self: Pin<&mut Self>, amt: usize) {
-        let me = self.project();
-        me.buf.advance(amt);
+        AsyncBufRead::consume(self, amt);
     }
 }
 
+#[cfg(feature = "tokio")]
 impl<R: AsyncRead + AsyncWrite> AsyncWrite for AsyncBufReader<R> {
     fn poll_write(
         self: Pin<&mut Self>,
@@ -220,3 +682,30 @@ impl<R: AsyncRead + AsyncWrite> AsyncWrite for AsyncBufReader<R> {
         self.get_pin_mut().poll_shutdown(cx)
     }
 }
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+impl<R: AsyncRead + AsyncWrite> AsyncWrite for AsyncBufReader<R> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_pin_mut().poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.get_pin_mut().poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_pin_mut().poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_pin_mut().poll_close(cx)
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use pin_project_lite::pin_project;
+
+use crate::io::{self as rt, AsyncRead, AsyncWrite, ReadBuf};
+use crate::{AsyncBufPassthrough, AsyncBufRead, AsyncBufReader, AsyncBufWriter};
+
+pin_project! {
+    /// The `AsyncBufStream` struct adds buffering to both the reading and the
+    /// writing side of a stream.
+    ///
+    /// It combines an [`AsyncBufReader`] and an [`AsyncBufWriter`], setting it
+    /// in passthrough mode applies to both sides.
+    pub struct AsyncBufStream<S> {
+        #[pin]
+        inner: AsyncBufReader<AsyncBufWriter<S>>,
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite> AsyncBufStream<S> {
+    /// Creates a new `AsyncBufStream` with the default chunk sizes.
+    pub fn new(stream: S) -> Self {
+        Self {
+            inner: AsyncBufReader::new(AsyncBufWriter::new(stream)),
+        }
+    }
+
+    /// Creates a new `AsyncBufStream` with the given chunk sizes for reading and writing.
+    pub fn with_chunk_sizes(read_chunk_size: usize, write_chunk_size: usize, stream: S) -> Self {
+        Self {
+            inner: AsyncBufReader::with_chunk_size(
+                read_chunk_size,
+                AsyncBufWriter::with_chunk_size(write_chunk_size, stream),
+            ),
+        }
+    }
+
+    /// Gets a reference to the underlying stream.
+    ///
+    /// It is inadvisable to directly read from or write to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref().get_ref()
+    }
+
+    /// Gets a mutable reference to the underlying stream.
+    ///
+    /// It is inadvisable to directly read from or write to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.inner.get_mut().get_mut()
+    }
+
+    /// Gets a pinned mutable reference to the underlying stream.
+    ///
+    /// It is inadvisable to directly read from or write to the underlying stream.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut S> {
+        self.project().inner.get_pin_mut().get_pin_mut()
+    }
+
+    /// Returns a reference to the buffered reader.
+    pub fn reader(&self) -> &AsyncBufReader<AsyncBufWriter<S>> {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the buffered reader.
+    pub fn reader_mut(&mut self) -> &mut AsyncBufReader<AsyncBufWriter<S>> {
+        &mut self.inner
+    }
+
+    /// Returns a reference to the buffered writer.
+    pub fn writer(&self) -> &AsyncBufWriter<S> {
+        self.inner.get_ref()
+    }
+
+    /// Returns a mutable reference to the buffered writer.
+    pub fn writer_mut(&mut self) -> &mut AsyncBufWriter<S> {
+        self.inner.get_mut()
+    }
+
+    fn poll_read_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        rt::poll_read_buf(self.project().inner, cx, buf)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite> AsyncBufPassthrough for AsyncBufStream<S> {
+    fn passthrough(&mut self, enabled: bool) {
+        self.inner.passthrough(enabled);
+        self.inner.get_mut().passthrough(enabled);
+    }
+}
+
+rt::impl_async_read!(impl<S> for AsyncBufStream<S> where S: AsyncRead + AsyncWrite);
+
+impl<S: AsyncRead + AsyncWrite> AsyncBufRead for AsyncBufStream<S> {
+    fn eof(self: Pin<&Self>) -> bool {
+        self.project_ref().inner.eof()
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        self.project_ref().inner.buf()
+    }
+
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        self.project().inner.poll_fill_buf(cx, amt)
+    }
+
+    fn poll_fill_buf_exact(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        self.project().inner.poll_fill_buf_exact(cx, amt)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.project().inner.consume(amt);
+    }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        self.project().inner.split_to(amt)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: AsyncRead + AsyncWrite> AsyncWrite for AsyncBufStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().inner.poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().inner.poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+impl<S: AsyncRead + AsyncWrite> AsyncWrite for AsyncBufStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().inner.poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().inner.poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
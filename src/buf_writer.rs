@@ -0,0 +1,244 @@
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use bytes::{Buf, BytesMut};
+use pin_project_lite::pin_project;
+
+use crate::AsyncBufPassthrough;
+use crate::io::{self as rt, AsyncRead, AsyncWrite, ReadBuf};
+
+pin_project! {
+    /// The `AsyncBufWriter` struct adds buffering to any writer.
+    ///
+    /// Small writes are accumulated in an internal buffer until it reaches the
+    /// flush threshold, at which point the buffered data is written to the
+    /// inner writer along with the new data using a vectored write.
+    ///
+    /// When the `AsyncBufWriter` is dropped, the contents of its buffer will be
+    /// discarded. Make sure to flush it before dropping it.
+    pub struct AsyncBufWriter<W> {
+        #[pin]
+        writer: W,
+        passthrough: bool,
+        buf: BytesMut,
+        flush_threshold: usize,
+    }
+}
+
+const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+impl<W: AsyncWrite> AsyncBufWriter<W> {
+    /// Creates a new `AsyncBufWriter` with the default chunk size.
+    pub fn new(writer: W) -> Self {
+        Self::with_chunk_size(DEFAULT_CHUNK_SIZE, writer)
+    }
+
+    /// Creates a new `AsyncBufWriter` with the given chunk size.
+    ///
+    /// The chunk size is the initial capacity of the buffer and the default flush threshold.
+    pub fn with_chunk_size(chunk_size: usize, writer: W) -> Self {
+        Self {
+            writer,
+            passthrough: false,
+            buf: BytesMut::with_capacity(chunk_size),
+            flush_threshold: chunk_size,
+        }
+    }
+
+    /// Sets the amount of buffered data at which the buffer is written to the inner writer.
+    pub fn with_flush_threshold(mut self, flush_threshold: usize) -> Self {
+        self.flush_threshold = flush_threshold;
+        self
+    }
+
+    /// Returns the amount of buffered data at which the buffer is written to the inner writer.
+    pub fn flush_threshold(&self) -> usize {
+        self.flush_threshold
+    }
+
+    /// Returns the current capacity of the internal buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Returns the current length of the internal buffer.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns true if the internal buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Gets a reference to the underlying writer.
+    ///
+    /// It is inadvisable to directly write to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// It is inadvisable to directly write to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Gets a pinned mutable reference to the underlying writer.
+    ///
+    /// It is inadvisable to directly write to the underlying writer.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut W> {
+        self.project().writer
+    }
+
+    /// Returns a reference to the internally buffered data.
+    pub fn buffer(&self) -> &[u8] {
+        self.buf.as_ref()
+    }
+
+    /// Writes all the buffered data to the inner writer.
+    fn poll_flush_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut me = self.project();
+        while !me.buf.is_empty() {
+            let n = ready!(me.writer.as_mut().poll_write(cx, me.buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(write_zero()));
+            }
+            me.buf.advance(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_buffered_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let mut me = self.project();
+        let len = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+        loop {
+            // Accumulate the data in the buffer while we are under the threshold
+            if !*me.passthrough && me.buf.len() + len < *me.flush_threshold {
+                for buf in bufs {
+                    me.buf.extend_from_slice(buf);
+                }
+                return Poll::Ready(Ok(len));
+            }
+
+            let buffered = me.buf.len();
+            if buffered == 0 {
+                return me.writer.poll_write_vectored(cx, bufs);
+            }
+
+            // Write the buffered data along with the new data in a single call
+            // if possible. Writers that don't support vectored writes will only
+            // write the buffered data.
+            let n = match bufs {
+                [buf] => {
+                    let slices = [IoSlice::new(me.buf), IoSlice::new(buf)];
+                    ready!(me.writer.as_mut().poll_write_vectored(cx, &slices))?
+                }
+                _ => ready!(me.writer.as_mut().poll_write(cx, me.buf))?,
+            };
+            if n == 0 {
+                return Poll::Ready(Err(write_zero()));
+            }
+            if n > buffered {
+                me.buf.clear();
+                return Poll::Ready(Ok(n - buffered));
+            }
+            me.buf.advance(n);
+        }
+    }
+}
+
+fn write_zero() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::WriteZero,
+        "failed to write the buffered data",
+    )
+}
+
+impl<W> AsyncBufPassthrough for AsyncBufWriter<W> {
+    fn passthrough(&mut self, enabled: bool) {
+        self.passthrough = enabled;
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W: AsyncWrite> AsyncWrite for AsyncBufWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_buffered_write(cx, &[IoSlice::new(buf)])
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_buffered_write(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        self.get_pin_mut().poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        self.get_pin_mut().poll_shutdown(cx)
+    }
+}
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+impl<W: AsyncWrite> AsyncWrite for AsyncBufWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_buffered_write(cx, &[IoSlice::new(buf)])
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_buffered_write(cx, bufs)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        self.get_pin_mut().poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        self.get_pin_mut().poll_close(cx)
+    }
+}
+
+impl<W: AsyncRead> AsyncBufWriter<W> {
+    fn poll_read_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        rt::poll_read_buf(self.project().writer, cx, buf)
+    }
+}
+
+rt::impl_async_read!(impl<W> for AsyncBufWriter<W> where W: AsyncRead);
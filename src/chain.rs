@@ -0,0 +1,196 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::{Buf, Bytes, BytesMut};
+use pin_project_lite::pin_project;
+
+use crate::io::{self as rt, AsyncRead, ReadBuf};
+use crate::{AsyncBufPassthrough, AsyncBufRead};
+
+pin_project! {
+    /// Reader for the [`chain`](crate::AsyncBufReadExt::chain) method.
+    ///
+    /// It reads the first reader until EOF, then the second one. When a
+    /// request spans both readers, the end of the first one and the start of
+    /// the second one are copied to an internal buffer, so they can be peeked
+    /// as a single slice.
+    ///
+    /// Passthrough mode is forwarded to both readers, so it still applies
+    /// once the first reader ends.
+    #[derive(Debug)]
+    pub struct Chain<A, B> {
+        #[pin]
+        first: A,
+        #[pin]
+        second: B,
+        done_first: bool,
+        passthrough: bool,
+        // Data coalesced across the boundary
+        coalesced: BytesMut,
+    }
+}
+
+pub(crate) fn chain<A, B>(first: A, second: B) -> Chain<A, B>
+where
+    A: AsyncBufRead,
+    B: AsyncBufRead,
+{
+    Chain {
+        first,
+        second,
+        done_first: false,
+        passthrough: false,
+        coalesced: BytesMut::new(),
+    }
+}
+
+impl<A, B> Chain<A, B> {
+    /// Gets references to the underlying readers.
+    pub fn get_ref(&self) -> (&A, &B) {
+        (&self.first, &self.second)
+    }
+
+    /// Gets mutable references to the underlying readers.
+    ///
+    /// Care should be taken to avoid modifying the internal I/O state of the
+    /// underlying readers as doing so may corrupt the chain.
+    pub fn get_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.first, &mut self.second)
+    }
+
+    /// Gets pinned mutable references to the underlying readers.
+    ///
+    /// Care should be taken to avoid modifying the internal I/O state of the
+    /// underlying readers as doing so may corrupt the chain.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> (Pin<&mut A>, Pin<&mut B>) {
+        let me = self.project();
+        (me.first, me.second)
+    }
+
+    /// Consumes this `Chain`, returning the underlying readers.
+    ///
+    /// The data that was coalesced across the boundary and not read yet is lost.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: AsyncRead, B: AsyncRead> Chain<A, B> {
+    fn poll_read_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let me = self.project();
+        if !me.coalesced.is_empty() {
+            let amt = std::cmp::min(me.coalesced.len(), buf.remaining());
+            buf.put_slice(&me.coalesced[..amt]);
+            me.coalesced.advance(amt);
+            return Poll::Ready(Ok(()));
+        }
+        if !*me.done_first {
+            let before = buf.filled().len();
+            ready!(rt::poll_read_buf(me.first, cx, buf))?;
+            if buf.filled().len() > before || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            *me.done_first = true;
+        }
+        rt::poll_read_buf(me.second, cx, buf)
+    }
+}
+
+rt::impl_async_read!(impl<A, B> for Chain<A, B> where A: AsyncRead, B: AsyncRead);
+
+impl<A: AsyncBufRead, B: AsyncBufRead> AsyncBufRead for Chain<A, B> {
+    fn eof(self: Pin<&Self>) -> bool {
+        let me = self.project_ref();
+        *me.done_first && me.second.eof()
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        let me = self.project_ref();
+        if !me.coalesced.is_empty() {
+            me.coalesced
+        } else if !*me.done_first {
+            me.first.buf()
+        } else {
+            me.second.buf()
+        }
+    }
+
+    fn poll_fill_buf<'a>(
+        mut self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        let mut me = self.as_mut().project();
+        if !*me.done_first {
+            let before = me.first.as_ref().buf().len();
+            let len = ready!(me.first.as_mut().poll_fill_buf(cx, amt))?.len();
+            // Like `poll_fill_buf_exact`, a reader that doesn't grow is done,
+            // unless it doesn't buffer because of passthrough mode
+            let done = me.first.as_ref().eof() || (len <= before && !*me.passthrough);
+            if len >= amt || !done {
+                return Poll::Ready(Ok(&self.into_ref().buf()[..len]));
+            }
+            // The request spans the boundary, move the rest of the first reader
+            me.coalesced
+                .extend_from_slice(&me.first.as_ref().buf()[..len]);
+            me.first.as_mut().consume(len);
+            *me.done_first = true;
+        }
+
+        if me.coalesced.is_empty() {
+            ready!(me.second.poll_fill_buf(cx, amt))?;
+        } else {
+            while me.coalesced.len() < amt {
+                let needed = amt - me.coalesced.len();
+                let buf = ready!(me.second.as_mut().poll_fill_buf(cx, needed))?;
+                if buf.is_empty() {
+                    break;
+                }
+                let len = std::cmp::min(buf.len(), needed);
+                me.coalesced.extend_from_slice(&buf[..len]);
+                me.second.as_mut().consume(len);
+            }
+        }
+        let buf = self.into_ref().buf();
+        Poll::Ready(Ok(&buf[..std::cmp::min(buf.len(), amt)]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let me = self.project();
+        if !me.coalesced.is_empty() {
+            me.coalesced.advance(amt);
+        } else if !*me.done_first {
+            me.first.consume(amt);
+        } else {
+            me.second.consume(amt);
+        }
+    }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        let me = self.project();
+        if !me.coalesced.is_empty() {
+            me.coalesced.split_to(amt).freeze()
+        } else if !*me.done_first {
+            me.first.split_to(amt)
+        } else {
+            me.second.split_to(amt)
+        }
+    }
+}
+
+impl<A, B> AsyncBufPassthrough for Chain<A, B>
+where
+    A: AsyncBufPassthrough,
+    B: AsyncBufPassthrough,
+{
+    fn passthrough(&mut self, enabled: bool) {
+        self.passthrough = enabled;
+        self.first.passthrough(enabled);
+        self.second.passthrough(enabled);
+    }
+}
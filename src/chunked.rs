@@ -0,0 +1,361 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use pin_project_lite::pin_project;
+
+use crate::io::{self as rt, ReadBuf};
+use crate::scan::{eof_error, poll_fill_buf_more, poll_scan};
+use crate::{AsyncBufRead, ChunkedError, LimitExceeded};
+
+pin_project! {
+    /// The `ChunkedDecoder` struct decodes an HTTP/1.1 body sent with the
+    /// `chunked` transfer coding.
+    ///
+    /// The decoded data is read from the buffer of the inner reader without
+    /// copying it, unless a request spans multiple chunks. Once the last chunk
+    /// and the trailer section are read, the decoder reports EOF and the inner
+    /// reader is positioned right after the body, so the next message can be
+    /// read from it.
+    pub struct ChunkedDecoder<R> {
+        #[pin]
+        reader: R,
+        state: State,
+        // Amount of data of the current chunk left in the inner reader
+        remaining: u64,
+        // Data of multiple chunks coalesced to satisfy a request
+        decoded: BytesMut,
+        scanned: usize,
+        extensions_size: usize,
+        trailers_size: usize,
+        trailers: Vec<(Bytes, Bytes)>,
+        max_line_length: usize,
+        max_extensions_size: usize,
+        max_trailers_size: usize,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Size,
+    Data,
+    Delimiter,
+    Trailers,
+    Done,
+}
+
+const DEFAULT_MAX_LINE_LENGTH: usize = 4 * 1024;
+const DEFAULT_MAX_EXTENSIONS_SIZE: usize = 16 * 1024;
+const DEFAULT_MAX_TRAILERS_SIZE: usize = 16 * 1024;
+
+impl<R: AsyncBufRead> ChunkedDecoder<R> {
+    /// Creates a new `ChunkedDecoder` with the default limits.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            state: State::Size,
+            remaining: 0,
+            decoded: BytesMut::new(),
+            scanned: 0,
+            extensions_size: 0,
+            trailers_size: 0,
+            trailers: Vec::new(),
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            max_extensions_size: DEFAULT_MAX_EXTENSIONS_SIZE,
+            max_trailers_size: DEFAULT_MAX_TRAILERS_SIZE,
+        }
+    }
+
+    /// Sets the maximum length of a chunk-size or trailer line, including
+    /// the chunk extensions and the CRLF. The default is 4 KiB.
+    pub fn with_max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// Sets the maximum total size of the chunk extensions of the body. The
+    /// default is 16 KiB.
+    pub fn with_max_extensions_size(mut self, max_extensions_size: usize) -> Self {
+        self.max_extensions_size = max_extensions_size;
+        self
+    }
+
+    /// Sets the maximum total size of the trailer section. The default is 16 KiB.
+    pub fn with_max_trailers_size(mut self, max_trailers_size: usize) -> Self {
+        self.max_trailers_size = max_trailers_size;
+        self
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().reader
+    }
+
+    /// Consumes this `ChunkedDecoder`, returning the underlying reader.
+    ///
+    /// Once the body is fully decoded, the reader is positioned right after it.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns true if the whole body, including the trailers, was read.
+    pub fn is_finished(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Returns the trailer fields as name and value pairs, once the whole body
+    /// was read.
+    pub fn trailers(&self) -> Option<&[(Bytes, Bytes)]> {
+        match self.state {
+            State::Done => Some(&self.trailers),
+            _ => None,
+        }
+    }
+
+    /// Advances the decoding by one step, reading from the inner reader if needed.
+    fn poll_step(self: Pin<&mut Self>, cx: &mut Context<'_>, amt: usize) -> Poll<io::Result<()>> {
+        let mut me = self.project();
+        match *me.state {
+            State::Size => {
+                let end = ready!(poll_line(
+                    me.reader.as_mut(),
+                    cx,
+                    me.scanned,
+                    *me.max_line_length
+                ))?;
+                let line = &me.reader.as_ref().buf()[..end];
+                let (size, extensions_size) = parse_size(line)?;
+                *me.extensions_size += extensions_size;
+                if *me.extensions_size > *me.max_extensions_size {
+                    return Poll::Ready(Err(LimitExceeded {
+                        limit: *me.max_extensions_size,
+                    }
+                    .into()));
+                }
+                me.reader.consume(end);
+                *me.remaining = size;
+                *me.state = if size == 0 {
+                    State::Trailers
+                } else {
+                    State::Data
+                };
+            }
+            State::Data if *me.remaining == 0 => {
+                *me.state = State::Delimiter;
+            }
+            State::Data => {
+                let buf = me.reader.as_ref().buf();
+                let len = std::cmp::min(buf.len() as u64, *me.remaining) as usize;
+                if me.decoded.is_empty() && amt as u64 <= *me.remaining {
+                    // The request fits in the current chunk, read it in place
+                    ready!(poll_fill_buf_more(me.reader, cx, amt))?;
+                } else if len > 0 {
+                    me.decoded.extend_from_slice(&buf[..len]);
+                    me.reader.consume(len);
+                    *me.remaining -= len as u64;
+                } else {
+                    let amt = std::cmp::min(*me.remaining, amt as u64) as usize;
+                    ready!(poll_fill_buf_more(me.reader, cx, amt))?;
+                }
+            }
+            State::Delimiter => {
+                let buf = ready!(me.reader.as_mut().poll_fill_buf_exact(cx, 2))?;
+                if buf != b"\r\n" {
+                    return Poll::Ready(Err(ChunkedError::InvalidDelimiter.into()));
+                }
+                me.reader.consume(2);
+                *me.state = State::Size;
+            }
+            State::Trailers => {
+                let end = ready!(poll_line(
+                    me.reader.as_mut(),
+                    cx,
+                    me.scanned,
+                    *me.max_line_length
+                ))?;
+                if end == 2 {
+                    if me.reader.as_ref().buf()[0] != b'\r' {
+                        return Poll::Ready(Err(ChunkedError::InvalidTrailer.into()));
+                    }
+                    me.reader.consume(end);
+                    *me.state = State::Done;
+                    return Poll::Ready(Ok(()));
+                }
+                *me.trailers_size += end;
+                if *me.trailers_size > *me.max_trailers_size {
+                    return Poll::Ready(Err(LimitExceeded {
+                        limit: *me.max_trailers_size,
+                    }
+                    .into()));
+                }
+                let trailer = parse_trailer(&me.reader.as_ref().buf()[..end])?;
+                let line = me.reader.split_to(end);
+                me.trailers
+                    .push((line.slice(trailer.0), line.slice(trailer.1)));
+            }
+            State::Done => {}
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_read_buf(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let rem = ready!(self.as_mut().poll_fill_buf(cx, buf.remaining()))?;
+        let amt = std::cmp::min(rem.len(), buf.remaining());
+        buf.put_slice(&rem[..amt]);
+        self.consume(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<R> for ChunkedDecoder<R> where R: AsyncBufRead);
+
+impl<R: AsyncBufRead> AsyncBufRead for ChunkedDecoder<R> {
+    fn eof(self: Pin<&Self>) -> bool {
+        self.state == State::Done
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        let me = self.project_ref();
+        if !me.decoded.is_empty() {
+            return me.decoded;
+        }
+        if *me.state != State::Data {
+            return &[];
+        }
+        let buf = me.reader.buf();
+        let len = std::cmp::min(buf.len() as u64, *me.remaining) as usize;
+        &buf[..len]
+    }
+
+    fn poll_fill_buf<'a>(
+        mut self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        // Return as soon as some data was decoded, like a single read would
+        let initial = self.as_ref().buf().len();
+        loop {
+            let len = self.as_ref().buf().len();
+            if len >= amt || len > initial || self.state == State::Done {
+                let buf = self.into_ref().buf();
+                return Poll::Ready(Ok(&buf[..std::cmp::min(len, amt)]));
+            }
+            ready!(self.as_mut().poll_step(cx, amt))?;
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let me = self.project();
+        if !me.decoded.is_empty() {
+            me.decoded.advance(amt);
+        } else {
+            assert!(
+                amt as u64 <= *me.remaining,
+                "cannot consume more than the buffered data"
+            );
+            me.reader.consume(amt);
+            *me.remaining -= amt as u64;
+        }
+    }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        let me = self.project();
+        if !me.decoded.is_empty() {
+            me.decoded.split_to(amt).freeze()
+        } else {
+            assert!(
+                amt as u64 <= *me.remaining,
+                "cannot consume more than the buffered data"
+            );
+            *me.remaining -= amt as u64;
+            me.reader.split_to(amt)
+        }
+    }
+}
+
+/// Fills the buffer of the reader until it contains a line, returning its
+/// length including the `\n`.
+fn poll_line<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    scanned: &mut usize,
+    limit: usize,
+) -> Poll<io::Result<usize>>
+where
+    R: AsyncBufRead + ?Sized,
+{
+    match ready!(poll_scan(reader.as_mut(), cx, b"\n", scanned, limit))? {
+        Some(end) => {
+            *scanned = 0;
+            Poll::Ready(Ok(end))
+        }
+        None => Poll::Ready(Err(eof_error(reader.as_ref()))),
+    }
+}
+
+/// Parses a chunk-size line, returning the size and the length of the extensions.
+fn parse_size(line: &[u8]) -> Result<(u64, usize), ChunkedError> {
+    let line = line
+        .strip_suffix(b"\r\n")
+        .ok_or(ChunkedError::InvalidSize)?;
+    let digits = line
+        .iter()
+        .position(|b| !b.is_ascii_hexdigit())
+        .unwrap_or(line.len());
+    if digits == 0 || digits > 16 {
+        return Err(ChunkedError::InvalidSize);
+    }
+    let size = std::str::from_utf8(&line[..digits])
+        .ok()
+        .and_then(|digits| u64::from_str_radix(digits, 16).ok())
+        .ok_or(ChunkedError::InvalidSize)?;
+
+    let extensions = &line[digits..];
+    match extensions.trim_ascii_start().first() {
+        None => Ok((size, 0)),
+        Some(b';') => Ok((size, extensions.len())),
+        Some(_) => Err(ChunkedError::InvalidSize),
+    }
+}
+
+/// Parses a trailer line, returning the ranges of the name and the value.
+fn parse_trailer(
+    line: &[u8],
+) -> Result<(std::ops::Range<usize>, std::ops::Range<usize>), ChunkedError> {
+    let line = line
+        .strip_suffix(b"\r\n")
+        .ok_or(ChunkedError::InvalidTrailer)?;
+    let colon = line
+        .iter()
+        .position(|b| *b == b':')
+        .ok_or(ChunkedError::InvalidTrailer)?;
+    let name = &line[..colon];
+    if name.is_empty() || name.iter().any(|b| b.is_ascii_whitespace()) {
+        return Err(ChunkedError::InvalidTrailer);
+    }
+    let value = &line[colon + 1..];
+    let start = colon + 1 + (value.len() - value.trim_ascii_start().len());
+    let end = colon + 1 + value.trim_ascii_end().len();
+    Ok((0..colon, start..std::cmp::max(start, end)))
+}
@@ -0,0 +1,142 @@
+//! Integration with [`tokio_util::codec`].
+//!
+//! The [`FramedRead`] stream runs a [`Decoder`] directly over the internal
+//! buffer of an [`AsyncBufReader`], so the reader can still be peeked or
+//! switched to passthrough mode between frames.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::BytesMut;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use tokio_util::codec::Decoder;
+
+use crate::io::AsyncRead;
+use crate::{AsyncBufPassthrough, AsyncBufRead, AsyncBufReader, BufferOverflow};
+
+pin_project! {
+    /// A [`Stream`] of frames decoded from an [`AsyncBufReader`].
+    ///
+    /// Unlike `tokio_util::codec::FramedRead`, it doesn't keep its own buffer.
+    /// The decoder works on the buffer of the reader, which can be recovered
+    /// with its leftover data by [`into_inner`](FramedRead::into_inner), for
+    /// example to decode a handshake and then switch to passthrough mode.
+    ///
+    /// The reader must not have an active [`Checkpoint`](crate::Checkpoint)
+    /// while frames are decoded, the stream returns an error otherwise. In
+    /// passthrough mode, the stream ends once the buffered frames are decoded
+    /// and can be polled again after passthrough mode is disabled.
+    ///
+    /// The [maximum buffer size](AsyncBufReader::with_max_buffer_size) of the
+    /// reader also applies to the data added by the decoder, the stream
+    /// returns a [`BufferOverflow`] error if it grows the buffer past it.
+    pub struct FramedRead<R, D> {
+        #[pin]
+        reader: AsyncBufReader<R>,
+        decoder: D,
+    }
+}
+
+impl<R: AsyncRead, D> FramedRead<R, D> {
+    /// Creates a new `FramedRead` decoding frames from `reader` with `decoder`.
+    pub fn new(reader: AsyncBufReader<R>, decoder: D) -> Self {
+        Self { reader, decoder }
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &AsyncBufReader<R> {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is safe to read from the underlying reader between frames.
+    pub fn get_mut(&mut self) -> &mut AsyncBufReader<R> {
+        &mut self.reader
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// It is safe to read from the underlying reader between frames.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut AsyncBufReader<R>> {
+        self.project().reader
+    }
+
+    /// Returns a reference to the decoder.
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the decoder.
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Consumes this `FramedRead`, returning the underlying reader.
+    ///
+    /// The data that was not decoded yet stays in the buffer of the reader.
+    pub fn into_inner(self) -> AsyncBufReader<R> {
+        self.reader
+    }
+
+    /// Consumes this `FramedRead`, returning the underlying reader and the decoder.
+    pub fn into_parts(self) -> (AsyncBufReader<R>, D) {
+        (self.reader, self.decoder)
+    }
+}
+
+impl<R, D> Stream for FramedRead<R, D>
+where
+    R: AsyncRead,
+    D: Decoder,
+{
+    type Item = Result<D::Item, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut me = self.project();
+        loop {
+            let eof = me.reader.as_ref().eof();
+            let max_buffer_size = me.reader.max_buffer_size();
+            let buf = me.reader.as_mut().buffer_mut()?;
+            let frame = me.decoder.decode(buf)?;
+            check_buffer_size(buf, max_buffer_size)?;
+            if let Some(frame) = frame {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            if eof {
+                // Let the decoder handle the leftover data
+                let frame = me.decoder.decode_eof(buf);
+                check_buffer_size(buf, max_buffer_size)?;
+                return Poll::Ready(frame.transpose());
+            }
+
+            // The reader doesn't fill its buffer in passthrough mode, so no
+            // progress means that no more frames can be decoded for now.
+            let before = me.reader.len();
+            ready!(me.reader.as_mut().poll_fill_buf(cx, before + 1))?;
+            if me.reader.len() <= before && !me.reader.as_ref().eof() {
+                return Poll::Ready(None);
+            }
+        }
+    }
+}
+
+impl<R, D> AsyncBufPassthrough for FramedRead<R, D> {
+    fn passthrough(&mut self, enabled: bool) {
+        self.reader.passthrough(enabled);
+    }
+}
+
+/// Fails if the decoder grew the buffer past the maximum buffer size.
+fn check_buffer_size(buf: &BytesMut, max_buffer_size: Option<usize>) -> io::Result<()> {
+    match max_buffer_size {
+        Some(max) if buf.len() > max => Err(BufferOverflow {
+            requested: buf.len(),
+            max_buffer_size: max,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
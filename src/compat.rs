@@ -0,0 +1,288 @@
+//! Adapters between [`AsyncBufRead`] and the `AsyncBufRead` trait of the runtime.
+//!
+//! [`AsyncBufReader`] implements both traits directly. These adapters are for
+//! the other buffered readers:
+//!
+//! - [`Compat`] turns a runtime buffered reader, like `tokio::io::BufReader`,
+//!   into an [`AsyncBufRead`]. The reader must implement [`RuntimeBuffer`].
+//! - [`RuntimeCompat`] turns an [`AsyncBufRead`], like a [`Take`] or a
+//!   `compression::Decompressor`, into a runtime buffered reader.
+//!
+//! [`AsyncBufReader`]: crate::AsyncBufReader
+//! [`Take`]: crate::Take
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::{Buf, Bytes, BytesMut};
+use pin_project_lite::pin_project;
+
+use crate::AsyncBufRead;
+use crate::io::{self as rt, AsyncRead, ReadBuf};
+
+pin_project! {
+    /// An [`AsyncBufRead`] reading from a runtime buffered reader.
+    ///
+    /// Requests covered by the buffer of the inner reader are served from it
+    /// directly. The runtime trait only refills an empty buffer, so a request
+    /// for more data than the inner reader holds is the only case where the
+    /// data is copied: it is moved to a buffer of the `Compat` until the
+    /// request is met, and served from there until it is consumed. The data
+    /// that was not requested stays in the inner reader, so each byte is only
+    /// buffered once.
+    ///
+    /// [`split_to`] copies the data it takes from the inner buffer, since the
+    /// runtime trait can't hand out owned bytes.
+    ///
+    /// [`split_to`]: AsyncBufRead::split_to
+    #[derive(Debug)]
+    pub struct Compat<R> {
+        #[pin]
+        inner: R,
+        buf: BytesMut,
+        eof: bool,
+    }
+}
+
+impl<R> Compat<R> {
+    /// Creates a new `Compat` reading from `inner`.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: BytesMut::new(),
+            eof: false,
+        }
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader while
+    /// data is buffered.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader while
+    /// data is buffered.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().inner
+    }
+
+    /// Consumes this `Compat`, returning the underlying reader.
+    ///
+    /// The data in the buffer of the `Compat` is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> Compat<R> {
+    fn poll_read_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let me = self.project();
+        if me.buf.is_empty() {
+            return rt::poll_read_buf(me.inner, cx, buf);
+        }
+        let amt = std::cmp::min(buf.remaining(), me.buf.len());
+        buf.put_slice(&me.buf[..amt]);
+        me.buf.advance(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<R> for Compat<R> where R: AsyncRead);
+
+impl<R: RuntimeBuffer> AsyncBufRead for Compat<R> {
+    fn eof(self: Pin<&Self>) -> bool {
+        self.eof
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        let me = self.project_ref();
+        if me.buf.is_empty() {
+            me.inner.get_ref().buffer()
+        } else {
+            me.buf
+        }
+    }
+
+    fn poll_fill_buf<'a>(
+        self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        let mut me = self.project();
+        if me.buf.is_empty() && amt > 0 && !*me.eof {
+            let len = ready!(me.inner.as_mut().poll_fill_buf(cx))?.len();
+            if len == 0 {
+                *me.eof = true;
+            }
+            if len >= amt || len == 0 {
+                let buf = me.inner.into_ref().get_ref().buffer();
+                return Poll::Ready(Ok(&buf[..std::cmp::min(buf.len(), amt)]));
+            }
+        }
+
+        // The request spans several reads of the inner reader
+        while me.buf.len() < amt && !*me.eof {
+            let chunk = ready!(me.inner.as_mut().poll_fill_buf(cx))?;
+            if chunk.is_empty() {
+                *me.eof = true;
+                break;
+            }
+            let len = std::cmp::min(chunk.len(), amt - me.buf.len());
+            me.buf.extend_from_slice(&chunk[..len]);
+            me.inner.as_mut().consume(len);
+        }
+        let len = std::cmp::min(me.buf.len(), amt);
+        Poll::Ready(Ok(&me.buf[..len]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let me = self.project();
+        if me.buf.is_empty() {
+            me.inner.consume(amt);
+        } else {
+            me.buf.advance(amt);
+        }
+    }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        let me = self.project();
+        if me.buf.is_empty() {
+            let bytes = Bytes::copy_from_slice(&me.inner.as_ref().get_ref().buffer()[..amt]);
+            me.inner.consume(amt);
+            bytes
+        } else {
+            me.buf.split_to(amt).freeze()
+        }
+    }
+}
+
+/// A runtime buffered reader that exposes its buffer without being polled.
+///
+/// [`Compat`] returns this buffer from [`AsyncBufRead::buf`], so the data
+/// doesn't have to be copied out of the inner reader.
+pub trait RuntimeBuffer: rt::AsyncBufRead {
+    /// Returns the data buffered by the reader.
+    ///
+    /// This is the data returned by the last call to `poll_fill_buf`, minus
+    /// the data consumed since.
+    fn buffer(&self) -> &[u8];
+}
+
+impl RuntimeBuffer for &[u8] {
+    fn buffer(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<T: RuntimeBuffer + Unpin + ?Sized> RuntimeBuffer for &mut T {
+    fn buffer(&self) -> &[u8] {
+        (**self).buffer()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: AsyncRead> RuntimeBuffer for tokio::io::BufReader<R> {
+    fn buffer(&self) -> &[u8] {
+        tokio::io::BufReader::buffer(self)
+    }
+}
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+impl<R: AsyncRead> RuntimeBuffer for futures_util::io::BufReader<R> {
+    fn buffer(&self) -> &[u8] {
+        futures_util::io::BufReader::buffer(self)
+    }
+}
+
+pin_project! {
+    /// A runtime buffered reader reading from an [`AsyncBufRead`].
+    ///
+    /// The buffer of the inner reader is returned as is, without copies.
+    /// The inner reader must not be in passthrough mode, since an empty
+    /// buffer means EOF for the runtime trait.
+    #[derive(Debug)]
+    pub struct RuntimeCompat<R> {
+        #[pin]
+        inner: R,
+    }
+}
+
+impl<R> RuntimeCompat<R> {
+    /// Creates a new `RuntimeCompat` reading from `inner`.
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().inner
+    }
+
+    /// Consumes this `RuntimeCompat`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncBufRead> RuntimeCompat<R> {
+    fn poll_read_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut inner = self.project().inner;
+        let rem = ready!(poll_fill_buf_any(inner.as_mut(), cx))?;
+        let amt = std::cmp::min(rem.len(), buf.remaining());
+        buf.put_slice(&rem[..amt]);
+        inner.consume(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<R> for RuntimeCompat<R> where R: AsyncBufRead);
+
+impl<R: AsyncBufRead> rt::AsyncBufRead for RuntimeCompat<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        poll_fill_buf_any(self.project().inner, cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.project().inner.consume(amt);
+    }
+}
+
+/// Returns the whole buffer of the reader, reading more data if it is empty.
+fn poll_fill_buf_any<'a, R>(
+    mut reader: Pin<&'a mut R>,
+    cx: &mut Context<'_>,
+) -> Poll<io::Result<&'a [u8]>>
+where
+    R: AsyncBufRead + ?Sized,
+{
+    ready!(reader.as_mut().poll_fill_buf(cx, 1))?;
+    Poll::Ready(Ok(reader.into_ref().buf()))
+}
@@ -0,0 +1,547 @@
+//! Streaming decompression of buffered readers.
+//!
+//! The [`Decompressor`] reads compressed data from any [`AsyncBufRead`] and
+//! implements [`AsyncBufRead`] on the decompressed data, so the content can be
+//! peeked like the raw stream. Each encoding is enabled by its own feature:
+//!
+//! - `compression-gzip`: [`Encoding::Gzip`]
+//! - `compression-deflate`: [`Encoding::Deflate`]
+//! - `compression-brotli`: [`Encoding::Brotli`]
+//! - `compression-zstd`: [`Encoding::Zstd`]
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::{Buf, Bytes, BytesMut};
+use pin_project_lite::pin_project;
+
+use crate::io::{self as rt, ReadBuf};
+use crate::scan::poll_fill_buf_more;
+use crate::{AsyncBufRead, LimitExceeded};
+
+const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// The minimum amount of space given to the decoder for each step.
+const OUTPUT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// A content encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// A single gzip member, as described in RFC 1952.
+    #[cfg(feature = "compression-gzip")]
+    Gzip,
+    /// A zlib stream, or a raw deflate stream as sent by some servers.
+    #[cfg(feature = "compression-deflate")]
+    Deflate,
+    /// A brotli stream.
+    #[cfg(feature = "compression-brotli")]
+    Brotli,
+    /// A single zstd frame.
+    #[cfg(feature = "compression-zstd")]
+    Zstd,
+}
+
+impl Encoding {
+    /// Returns the encoding matching a `Content-Encoding` value, ignoring case.
+    ///
+    /// Returns `None` if the encoding is unknown or its feature is disabled.
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        match name.as_slice() {
+            #[cfg(feature = "compression-gzip")]
+            b"gzip" | b"x-gzip" => Some(Self::Gzip),
+            #[cfg(feature = "compression-deflate")]
+            b"deflate" => Some(Self::Deflate),
+            #[cfg(feature = "compression-brotli")]
+            b"br" => Some(Self::Brotli),
+            #[cfg(feature = "compression-zstd")]
+            b"zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned when the compressed data is malformed.
+///
+/// It is wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidData`]
+/// and can be retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecompressError {
+    /// The header of the stream is invalid.
+    InvalidHeader,
+    /// The checksum or length in the trailer of the stream doesn't match.
+    InvalidTrailer,
+    /// The compressed data is invalid.
+    InvalidData,
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => f.write_str("invalid compressed stream header"),
+            Self::InvalidTrailer => f.write_str("invalid compressed stream trailer"),
+            Self::InvalidData => f.write_str("invalid compressed data"),
+        }
+    }
+}
+
+impl std::error::Error for DecompressError {}
+
+impl From<DecompressError> for io::Error {
+    fn from(err: DecompressError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+pin_project! {
+    /// The `Decompressor` struct decompresses the data of a buffered reader.
+    ///
+    /// The compressed data is pulled from the buffer of the inner reader and
+    /// only the consumed part is removed from it. Once the end of the stream
+    /// is decoded, the decompressor reports EOF and any data following the
+    /// stream is left in the inner reader.
+    ///
+    /// The total amount of decompressed data is limited to protect against
+    /// decompression bombs, see [`with_max_decompressed_size`].
+    ///
+    /// [`with_max_decompressed_size`]: Decompressor::with_max_decompressed_size
+    pub struct Decompressor<R> {
+        #[pin]
+        reader: R,
+        encoding: Encoding,
+        codec: Codec,
+        decoded: BytesMut,
+        total_out: usize,
+        max_decompressed_size: usize,
+        done: bool,
+    }
+}
+
+impl<R: AsyncBufRead> Decompressor<R> {
+    /// Creates a new `Decompressor` with the default maximum decompressed size of 64 MiB.
+    pub fn new(encoding: Encoding, reader: R) -> Self {
+        Self {
+            reader,
+            encoding,
+            codec: Codec::new(encoding),
+            decoded: BytesMut::new(),
+            total_out: 0,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            done: false,
+        }
+    }
+
+    /// Sets the maximum total size of the decompressed data.
+    ///
+    /// Decompressing more data fails with an error of kind
+    /// [`InvalidData`](io::ErrorKind::InvalidData) wrapping [`LimitExceeded`].
+    pub fn with_max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+
+    /// Returns the maximum total size of the decompressed data.
+    pub fn max_decompressed_size(&self) -> usize {
+        self.max_decompressed_size
+    }
+
+    /// Returns the encoding of the compressed data.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().reader
+    }
+
+    /// Consumes this `Decompressor`, returning the underlying reader.
+    ///
+    /// The decompressed data that was not read yet is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns true once the end of the compressed stream was decoded.
+    pub fn is_finished(&self) -> bool {
+        self.done
+    }
+
+    /// Advances the decompression by one step, reading from the inner reader if needed.
+    fn poll_step(self: Pin<&mut Self>, cx: &mut Context<'_>, amt: usize) -> Poll<io::Result<()>> {
+        let mut me = self.project();
+        if *me.total_out > *me.max_decompressed_size {
+            return Poll::Ready(Err(LimitExceeded {
+                limit: *me.max_decompressed_size,
+            }
+            .into()));
+        }
+
+        // Once the limit is reached, a single byte of room detects the overflow
+        let allowed = std::cmp::max(*me.max_decompressed_size - *me.total_out, 1);
+        let len = me.decoded.len();
+        let space = std::cmp::max(amt.saturating_sub(len), OUTPUT_CHUNK_SIZE);
+        me.decoded.resize(len + std::cmp::min(space, allowed), 0);
+
+        let input = me.reader.as_ref().buf();
+        let input_len = input.len();
+        let progress = me.codec.decode(input, &mut me.decoded[len..]);
+        let produced = progress.as_ref().map_or(0, |progress| progress.produced);
+        me.decoded.truncate(len + produced);
+        let progress = progress?;
+
+        me.reader.as_mut().consume(progress.consumed);
+        *me.total_out += produced;
+        if *me.total_out > *me.max_decompressed_size {
+            return Poll::Ready(Err(LimitExceeded {
+                limit: *me.max_decompressed_size,
+            }
+            .into()));
+        }
+        if progress.done {
+            *me.done = true;
+        } else if progress.consumed == 0 && produced == 0 {
+            ready!(poll_fill_buf_more(me.reader, cx, input_len + 1))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_read_buf(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let rem = ready!(self.as_mut().poll_fill_buf(cx, buf.remaining()))?;
+        let amt = std::cmp::min(rem.len(), buf.remaining());
+        buf.put_slice(&rem[..amt]);
+        self.consume(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<R> for Decompressor<R> where R: AsyncBufRead);
+
+impl<R: AsyncBufRead> AsyncBufRead for Decompressor<R> {
+    fn eof(self: Pin<&Self>) -> bool {
+        self.done
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        self.project_ref().decoded
+    }
+
+    fn poll_fill_buf<'a>(
+        mut self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        // Return as soon as some data was decompressed, like a single read would
+        let initial = self.decoded.len();
+        loop {
+            let len = self.decoded.len();
+            if len >= amt || len > initial || self.done {
+                let buf = self.into_ref().buf();
+                return Poll::Ready(Ok(&buf[..std::cmp::min(len, amt)]));
+            }
+            ready!(self.as_mut().poll_step(cx, amt))?;
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.project().decoded.advance(amt);
+    }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        self.project().decoded.split_to(amt).freeze()
+    }
+}
+
+/// The result of a decoding step.
+struct Progress {
+    consumed: usize,
+    produced: usize,
+    done: bool,
+}
+
+enum Codec {
+    #[cfg(feature = "compression-gzip")]
+    Gzip(Box<Gzip>),
+    // The zlib wrapper is detected from the first bytes
+    #[cfg(feature = "compression-deflate")]
+    Deflate(Option<Box<flate2::Decompress>>),
+    #[cfg(feature = "compression-brotli")]
+    Brotli(Box<BrotliState>),
+    #[cfg(feature = "compression-zstd")]
+    Zstd(Box<zstd::stream::raw::Decoder<'static>>),
+}
+
+#[cfg(feature = "compression-brotli")]
+type BrotliState = brotli_decompressor::BrotliState<
+    brotli_decompressor::StandardAlloc,
+    brotli_decompressor::StandardAlloc,
+    brotli_decompressor::StandardAlloc,
+>;
+
+impl Codec {
+    fn new(encoding: Encoding) -> Self {
+        match encoding {
+            #[cfg(feature = "compression-gzip")]
+            Encoding::Gzip => Self::Gzip(Box::default()),
+            #[cfg(feature = "compression-deflate")]
+            Encoding::Deflate => Self::Deflate(None),
+            #[cfg(feature = "compression-brotli")]
+            Encoding::Brotli => {
+                use brotli_decompressor::StandardAlloc;
+                Self::Brotli(Box::new(BrotliState::new(
+                    StandardAlloc::default(),
+                    StandardAlloc::default(),
+                    StandardAlloc::default(),
+                )))
+            }
+            #[cfg(feature = "compression-zstd")]
+            Encoding::Zstd => Self::Zstd(Box::new(
+                zstd::stream::raw::Decoder::new().expect("failed to allocate a zstd context"),
+            )),
+        }
+    }
+
+    /// Decodes as much of `input` as possible into `output`.
+    ///
+    /// No progress means that more input is needed.
+    fn decode(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<Progress> {
+        match self {
+            #[cfg(feature = "compression-gzip")]
+            Self::Gzip(gzip) => gzip.decode(input, output),
+            #[cfg(feature = "compression-deflate")]
+            Self::Deflate(inflate) => {
+                let inflate = match inflate {
+                    Some(inflate) => inflate,
+                    None if input.len() < 2 => {
+                        return Ok(Progress {
+                            consumed: 0,
+                            produced: 0,
+                            done: false,
+                        });
+                    }
+                    None => {
+                        let zlib = input[0] & 0x0f == 8
+                            && u16::from_be_bytes([input[0], input[1]]) % 31 == 0;
+                        inflate.insert(Box::new(flate2::Decompress::new(zlib)))
+                    }
+                };
+                inflate_step(inflate, input, output)
+            }
+            #[cfg(feature = "compression-brotli")]
+            Self::Brotli(state) => {
+                use brotli_decompressor::{BrotliDecompressStream, BrotliResult};
+
+                let mut available_in = input.len();
+                let mut input_offset = 0;
+                let mut available_out = output.len();
+                let mut output_offset = 0;
+                let mut total_out = 0;
+                let result = BrotliDecompressStream(
+                    &mut available_in,
+                    &mut input_offset,
+                    input,
+                    &mut available_out,
+                    &mut output_offset,
+                    output,
+                    &mut total_out,
+                    state,
+                );
+                if let BrotliResult::ResultFailure = result {
+                    return Err(DecompressError::InvalidData.into());
+                }
+                Ok(Progress {
+                    consumed: input_offset,
+                    produced: output_offset,
+                    done: matches!(result, BrotliResult::ResultSuccess),
+                })
+            }
+            #[cfg(feature = "compression-zstd")]
+            Self::Zstd(decoder) => {
+                use zstd::stream::raw::Operation;
+
+                let status = decoder
+                    .run_on_buffers(input, output)
+                    .map_err(|_| DecompressError::InvalidData)?;
+                Ok(Progress {
+                    consumed: status.bytes_read,
+                    produced: status.bytes_written,
+                    done: status.remaining == 0,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "compression-gzip", feature = "compression-deflate"))]
+fn inflate_step(
+    inflate: &mut flate2::Decompress,
+    input: &[u8],
+    output: &mut [u8],
+) -> io::Result<Progress> {
+    let (total_in, total_out) = (inflate.total_in(), inflate.total_out());
+    let status = inflate
+        .decompress(input, output, flate2::FlushDecompress::None)
+        .map_err(|_| DecompressError::InvalidData)?;
+    Ok(Progress {
+        consumed: (inflate.total_in() - total_in) as usize,
+        produced: (inflate.total_out() - total_out) as usize,
+        done: status == flate2::Status::StreamEnd,
+    })
+}
+
+#[cfg(feature = "compression-gzip")]
+const GZIP_HEADER_LEN: usize = 10;
+
+#[cfg(feature = "compression-gzip")]
+const GZIP_TRAILER_LEN: usize = 8;
+
+/// The maximum size of a gzip header, including the optional fields.
+#[cfg(feature = "compression-gzip")]
+const GZIP_MAX_HEADER_LEN: usize = 64 * 1024;
+
+#[cfg(feature = "compression-gzip")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum GzipState {
+    #[default]
+    Header,
+    Body,
+    Trailer,
+}
+
+#[cfg(feature = "compression-gzip")]
+struct Gzip {
+    state: GzipState,
+    inflate: flate2::Decompress,
+    crc: flate2::Crc,
+}
+
+#[cfg(feature = "compression-gzip")]
+impl Default for Gzip {
+    fn default() -> Self {
+        Self {
+            state: GzipState::default(),
+            inflate: flate2::Decompress::new(false),
+            crc: flate2::Crc::new(),
+        }
+    }
+}
+
+#[cfg(feature = "compression-gzip")]
+impl Gzip {
+    fn decode(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<Progress> {
+        let mut progress = Progress {
+            consumed: 0,
+            produced: 0,
+            done: false,
+        };
+        match self.state {
+            GzipState::Header => {
+                if let Some(len) = parse_gzip_header(input)? {
+                    progress.consumed = len;
+                    self.state = GzipState::Body;
+                }
+            }
+            GzipState::Body => {
+                progress = inflate_step(&mut self.inflate, input, output)?;
+                self.crc.update(&output[..progress.produced]);
+                if progress.done {
+                    progress.done = false;
+                    self.state = GzipState::Trailer;
+                }
+            }
+            GzipState::Trailer => {
+                if input.len() >= GZIP_TRAILER_LEN {
+                    let crc = u32::from_le_bytes([input[0], input[1], input[2], input[3]]);
+                    let size = u32::from_le_bytes([input[4], input[5], input[6], input[7]]);
+                    if crc != self.crc.sum() || size != self.crc.amount() {
+                        return Err(DecompressError::InvalidTrailer.into());
+                    }
+                    progress.consumed = GZIP_TRAILER_LEN;
+                    progress.done = true;
+                }
+            }
+        }
+        Ok(progress)
+    }
+}
+
+/// Parses a gzip member header.
+///
+/// Returns `None` if more data is needed. The header is invalid if it is
+/// larger than [`GZIP_MAX_HEADER_LEN`], so the optional fields can't make the
+/// buffer grow without bound.
+#[cfg(feature = "compression-gzip")]
+fn parse_gzip_header(buf: &[u8]) -> Result<Option<usize>, DecompressError> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+    const RESERVED: u8 = 0xe0;
+
+    if buf.len() < GZIP_HEADER_LEN {
+        return Ok(None);
+    }
+    // Magic number and deflate compression method
+    let flags = buf[3];
+    if buf[..3] != [0x1f, 0x8b, 0x08] || flags & RESERVED != 0 {
+        return Err(DecompressError::InvalidHeader);
+    }
+
+    // Past the maximum size, a header that needs more data is invalid
+    let incomplete = match buf.len() > GZIP_MAX_HEADER_LEN {
+        true => Err(DecompressError::InvalidHeader),
+        false => Ok(None),
+    };
+    let buf = &buf[..std::cmp::min(buf.len(), GZIP_MAX_HEADER_LEN)];
+
+    let mut len = GZIP_HEADER_LEN;
+    if flags & FEXTRA != 0 {
+        let Some(extra) = buf.get(len..len + 2) else {
+            return incomplete;
+        };
+        len += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            // Zero-terminated string
+            let Some(end) = buf
+                .get(len..)
+                .and_then(|rest| rest.iter().position(|b| *b == 0))
+            else {
+                return incomplete;
+            };
+            len += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        len += 2;
+    }
+    if len > GZIP_MAX_HEADER_LEN {
+        return Err(DecompressError::InvalidHeader);
+    }
+    if buf.len() < len {
+        return incomplete;
+    }
+    Ok(Some(len))
+}
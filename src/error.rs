@@ -0,0 +1,122 @@
+use std::fmt;
+use std::io;
+
+/// The error returned when the inner reader reached EOF before the requested
+/// amount of data could be buffered.
+///
+/// It is wrapped in an [`io::Error`] of kind [`io::ErrorKind::UnexpectedEof`]
+/// and can be retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnexpectedEof {
+    /// The amount of data that was requested.
+    pub requested: usize,
+    /// The amount of data that was available in the buffer.
+    pub partial: usize,
+}
+
+impl fmt::Display for UnexpectedEof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unexpected EOF, requested {} bytes but only {} are available",
+            self.requested, self.partial
+        )
+    }
+}
+
+impl std::error::Error for UnexpectedEof {}
+
+impl From<UnexpectedEof> for io::Error {
+    fn from(err: UnexpectedEof) -> Self {
+        io::Error::new(io::ErrorKind::UnexpectedEof, err)
+    }
+}
+
+/// The error returned when a request would grow the internal buffer past its
+/// maximum size.
+///
+/// It is wrapped in an [`io::Error`] of kind [`io::ErrorKind::OutOfMemory`]
+/// and can be retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferOverflow {
+    /// The amount of data that was requested, including the consumed data
+    /// retained for checkpoints.
+    pub requested: usize,
+    /// The maximum size of the buffer.
+    pub max_buffer_size: usize,
+}
+
+impl fmt::Display for BufferOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "buffer overflow, requested {} bytes but the maximum buffer size is {}",
+            self.requested, self.max_buffer_size
+        )
+    }
+}
+
+impl std::error::Error for BufferOverflow {}
+
+impl From<BufferOverflow> for io::Error {
+    fn from(err: BufferOverflow) -> Self {
+        io::Error::new(io::ErrorKind::OutOfMemory, err)
+    }
+}
+
+/// The error returned when the data doesn't fit within the configured limit,
+/// for example when a delimiter is not found within the maximum length.
+///
+/// It is wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidData`]
+/// and can be retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// The limit that was exceeded.
+    pub limit: usize,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "limit of {} bytes exceeded", self.limit)
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl From<LimitExceeded> for io::Error {
+    fn from(err: LimitExceeded) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// The error returned when a chunked body is malformed.
+///
+/// It is wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidData`]
+/// and can be retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkedError {
+    /// The chunk-size line is invalid.
+    InvalidSize,
+    /// The chunk data is not followed by a CRLF.
+    InvalidDelimiter,
+    /// A trailer field is invalid.
+    InvalidTrailer,
+}
+
+impl fmt::Display for ChunkedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSize => f.write_str("invalid chunk size"),
+            Self::InvalidDelimiter => f.write_str("invalid chunk delimiter"),
+            Self::InvalidTrailer => f.write_str("invalid chunk trailer"),
+        }
+    }
+}
+
+impl std::error::Error for ChunkedError {}
+
+impl From<ChunkedError> for io::Error {
+    fn from(err: ChunkedError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
@@ -0,0 +1,336 @@
+//! Length-prefixed framing.
+//!
+//! The [`LengthDelimited`] reader splits a stream into frames prefixed by
+//! their length. The header is peeked from the buffer of the underlying
+//! reader and the frames are split from it, so they are returned as [`Bytes`]
+//! without extra copies when reading from an [`AsyncBufReader`].
+//!
+//! [`AsyncBufReader`]: crate::AsyncBufReader
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::Bytes;
+
+use crate::scan::poll_grow;
+use crate::{AsyncBufPassthrough, AsyncBufRead, LimitExceeded, UnexpectedEof};
+
+/// The maximum number of digits of a netstring length.
+const NETSTRING_MAX_DIGITS: usize = 20;
+
+/// The maximum number of bytes of a varint length.
+const VARINT_MAX_LEN: usize = 10;
+
+const DEFAULT_MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+
+/// The encoding of the length of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthFormat {
+    /// A single byte.
+    U8,
+    /// Two bytes in big-endian order.
+    U16Be,
+    /// Two bytes in little-endian order.
+    U16Le,
+    /// Four bytes in big-endian order.
+    U32Be,
+    /// Four bytes in little-endian order.
+    U32Le,
+    /// An unsigned LEB128 varint, as used by Protocol Buffers.
+    Varint,
+    /// A [netstring](https://cr.yp.to/proto/netstrings.txt), the length is
+    /// written in ASCII digits followed by `:` and the frame by `,`.
+    Netstring,
+}
+
+impl LengthFormat {
+    /// Returns the minimum number of bytes needed to parse the header.
+    fn min_header_len(self) -> usize {
+        match self {
+            Self::U8 | Self::Varint => 1,
+            Self::U16Be | Self::U16Le => 2,
+            Self::U32Be | Self::U32Le => 4,
+            Self::Netstring => 2,
+        }
+    }
+
+    /// Parses the header of a frame.
+    ///
+    /// Returns `None` if more data is needed.
+    fn parse(self, buf: &[u8]) -> Result<Option<Header>, FrameError> {
+        let min = self.min_header_len();
+        if buf.len() < min {
+            return Ok(None);
+        }
+        let fixed = |frame_len: u32| Header {
+            len: min,
+            frame_len: frame_len as usize,
+            trailer_len: 0,
+        };
+        let header = match self {
+            Self::U8 => fixed(buf[0] as u32),
+            Self::U16Be => fixed(u16::from_be_bytes([buf[0], buf[1]]) as u32),
+            Self::U16Le => fixed(u16::from_le_bytes([buf[0], buf[1]]) as u32),
+            Self::U32Be => fixed(u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]])),
+            Self::U32Le => fixed(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])),
+            Self::Varint => {
+                let mut frame_len: u64 = 0;
+                for (i, byte) in buf.iter().take(VARINT_MAX_LEN).enumerate() {
+                    let value = (*byte & 0x7f) as u64;
+                    if i == VARINT_MAX_LEN - 1 && value > 1 {
+                        return Err(FrameError::InvalidHeader);
+                    }
+                    frame_len |= value << (7 * i);
+                    if byte & 0x80 == 0 {
+                        let frame_len =
+                            usize::try_from(frame_len).map_err(|_| FrameError::InvalidHeader)?;
+                        return Ok(Some(Header {
+                            len: i + 1,
+                            frame_len,
+                            trailer_len: 0,
+                        }));
+                    }
+                }
+                if buf.len() >= VARINT_MAX_LEN {
+                    return Err(FrameError::InvalidHeader);
+                }
+                return Ok(None);
+            }
+            Self::Netstring => {
+                let digits = buf
+                    .iter()
+                    .take(NETSTRING_MAX_DIGITS + 1)
+                    .position(|b| !b.is_ascii_digit())
+                    .unwrap_or(buf.len());
+                if digits > NETSTRING_MAX_DIGITS {
+                    return Err(FrameError::InvalidHeader);
+                }
+                if digits == buf.len() {
+                    return Ok(None);
+                }
+                // Leading zeros are not allowed
+                if digits == 0 || buf[digits] != b':' || (buf[0] == b'0' && digits > 1) {
+                    return Err(FrameError::InvalidHeader);
+                }
+                let frame_len = std::str::from_utf8(&buf[..digits])
+                    .ok()
+                    .and_then(|digits| digits.parse().ok())
+                    .ok_or(FrameError::InvalidHeader)?;
+                Header {
+                    len: digits + 1,
+                    frame_len,
+                    trailer_len: 1,
+                }
+            }
+        };
+        Ok(Some(header))
+    }
+}
+
+/// The layout of a frame.
+struct Header {
+    len: usize,
+    frame_len: usize,
+    trailer_len: usize,
+}
+
+/// The error returned when a frame is malformed.
+///
+/// It is wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidData`]
+/// and can be retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// The length of the frame is invalid.
+    InvalidHeader,
+    /// The frame is not followed by the expected trailer.
+    InvalidTrailer,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => f.write_str("invalid frame length"),
+            Self::InvalidTrailer => f.write_str("invalid frame trailer"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<FrameError> for io::Error {
+    fn from(err: FrameError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// The `LengthDelimited` struct reads length-prefixed frames from a buffered
+/// reader.
+///
+/// The frames are returned without their header and trailer. Nothing is
+/// consumed from the underlying reader until a full frame is available, so
+/// the underlying reader can be used directly between frames, for example to
+/// enable passthrough mode.
+#[derive(Debug)]
+pub struct LengthDelimited<R> {
+    reader: R,
+    format: LengthFormat,
+    max_frame_size: usize,
+}
+
+impl<R> LengthDelimited<R> {
+    /// Creates a new `LengthDelimited` with the default maximum frame size of 8 MiB.
+    pub fn new(format: LengthFormat, reader: R) -> Self {
+        Self {
+            reader,
+            format,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Sets the maximum size of a frame, excluding its header and trailer.
+    ///
+    /// Reading a larger frame fails with an error of kind
+    /// [`InvalidData`](io::ErrorKind::InvalidData) wrapping [`LimitExceeded`].
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Returns the maximum size of a frame.
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Returns the encoding of the length of the frames.
+    pub fn format(&self) -> LengthFormat {
+        self.format
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is safe to read from the underlying reader between frames.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Consumes this `LengthDelimited`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> LengthDelimited<R> {
+    /// Attempts to read the next frame.
+    ///
+    /// On success, returns `Poll::Ready(Ok(Some(frame)))`, or
+    /// `Poll::Ready(Ok(None))` if the reader reached EOF between frames.
+    pub fn poll_read_frame(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<Bytes>>> {
+        let mut reader = Pin::new(&mut self.reader);
+
+        let header = loop {
+            let buf = reader.as_ref().buf();
+            if let Some(header) = self.format.parse(buf)? {
+                break header;
+            }
+            let before = buf.len();
+            let amt = std::cmp::max(before + 1, self.format.min_header_len());
+            ready!(reader.as_mut().poll_fill_buf(cx, amt))?;
+            // A buffer that stops growing before EOF, for example in
+            // passthrough mode, fails instead of ending the frames
+            if reader.as_ref().buf().len() <= before && !ready!(poll_grow(reader.as_mut(), cx))? {
+                if before == 0 {
+                    return Poll::Ready(Ok(None));
+                }
+                return Poll::Ready(Err(UnexpectedEof {
+                    requested: amt,
+                    partial: before,
+                }
+                .into()));
+            }
+        };
+
+        if header.frame_len > self.max_frame_size {
+            return Poll::Ready(Err(LimitExceeded {
+                limit: self.max_frame_size,
+            }
+            .into()));
+        }
+        let Some(len) = header
+            .len
+            .checked_add(header.frame_len)
+            .and_then(|len| len.checked_add(header.trailer_len))
+        else {
+            return Poll::Ready(Err(FrameError::InvalidHeader.into()));
+        };
+        let buf = ready!(reader.as_mut().poll_fill_buf_exact(cx, len))?;
+        if self.format == LengthFormat::Netstring && buf[len - 1] != b',' {
+            return Poll::Ready(Err(FrameError::InvalidTrailer.into()));
+        }
+
+        reader.as_mut().consume(header.len);
+        let frame = reader.as_mut().split_to(header.frame_len);
+        reader.consume(header.trailer_len);
+        Poll::Ready(Ok(Some(frame)))
+    }
+
+    /// Reads the next frame.
+    ///
+    /// Equivalent to:
+    ///
+    /// ```ignore
+    /// async fn read_frame(&mut self) -> io::Result<Option<Bytes>>;
+    /// ```
+    ///
+    /// Returns `None` if the reader reached EOF between frames.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an I/O error if the underlying reader was
+    /// read, but returned an error.
+    ///
+    /// If the frame is larger than the maximum frame size, an error wrapping
+    /// [`LimitExceeded`] is returned. If the frame is malformed, an error
+    /// wrapping a [`FrameError`] is returned. If the reader reaches EOF in
+    /// the middle of a frame, an error wrapping [`UnexpectedEof`] is returned.
+    /// If the buffer stops growing before EOF, for example in passthrough
+    /// mode, an error of kind [`Other`](io::ErrorKind::Other) is returned. In
+    /// all cases, no data was consumed.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If you use it as the event in a
+    /// `select!` statement and some other branch completes first,
+    /// then it is guaranteed that no data was read.
+    pub fn read_frame(&mut self) -> ReadFrame<'_, R> {
+        ReadFrame { framing: self }
+    }
+}
+
+impl<R: AsyncBufPassthrough> AsyncBufPassthrough for LengthDelimited<R> {
+    fn passthrough(&mut self, enabled: bool) {
+        self.reader.passthrough(enabled);
+    }
+}
+
+/// Future for the [`read_frame`](LengthDelimited::read_frame) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadFrame<'a, R> {
+    framing: &'a mut LengthDelimited<R>,
+}
+
+impl<R: AsyncBufRead + Unpin> Future for ReadFrame<'_, R> {
+    type Output = io::Result<Option<Bytes>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().framing.poll_read_frame(cx)
+    }
+}
@@ -0,0 +1,342 @@
+//! Parsing of HTTP/1.x heads with [`httparse`].
+//!
+//! The [`read_request_head`] and [`read_response_head`] methods grow the
+//! buffer of the reader until a full head is available, then split it from
+//! the buffer. The parts of the returned head are [`Bytes`] slices of it, so
+//! no data is copied when reading from an [`AsyncBufReader`].
+//!
+//! The body is left in the buffer, so it can be read with a
+//! [`ChunkedDecoder`] or forwarded in passthrough mode.
+//!
+//! [`read_request_head`]: crate::AsyncBufReadExt::read_request_head
+//! [`read_response_head`]: crate::AsyncBufReadExt::read_response_head
+//! [`AsyncBufReader`]: crate::AsyncBufReader
+//! [`ChunkedDecoder`]: crate::ChunkedDecoder
+
+use std::future::Future;
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::Bytes;
+use httparse::Status;
+
+use crate::scan::{eof_error, poll_scan};
+use crate::{AsyncBufRead, LimitExceeded};
+
+/// The error returned when a head is malformed.
+///
+/// It is wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidData`]
+/// and can be retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+pub use httparse::Error as ParseError;
+
+const DEFAULT_MAX_SIZE: usize = 64 * 1024;
+const DEFAULT_MAX_HEADERS: usize = 100;
+
+/// The limits enforced while reading a head.
+///
+/// By default, a head can be up to 64 KiB and have up to 100 headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadLimits {
+    /// The maximum size of the head, including the empty line ending it.
+    pub max_size: usize,
+    /// The maximum number of headers.
+    pub max_headers: usize,
+}
+
+impl Default for HeadLimits {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+            max_headers: DEFAULT_MAX_HEADERS,
+        }
+    }
+}
+
+/// A header field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// The name of the header, as it was received.
+    pub name: Bytes,
+    /// The value of the header, without surrounding whitespace.
+    pub value: Bytes,
+}
+
+/// The head of an HTTP/1.x request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestHead {
+    /// The request method.
+    pub method: Bytes,
+    /// The request target.
+    pub path: Bytes,
+    /// The minor version of HTTP/1.x.
+    pub minor_version: u8,
+    /// The headers, in the order they were received.
+    pub headers: Vec<Header>,
+}
+
+impl RequestHead {
+    /// Returns the value of the first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&Bytes> {
+        find_header(&self.headers, name)
+    }
+
+    fn from_spans(head: &Bytes, spans: Spans) -> Self {
+        let headers = spans.to_headers(head);
+        let [method, path] = spans.start_line;
+        Self {
+            method: head.slice(method),
+            path: head.slice(path),
+            minor_version: spans.minor_version,
+            headers,
+        }
+    }
+}
+
+/// The head of an HTTP/1.x response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseHead {
+    /// The minor version of HTTP/1.x.
+    pub minor_version: u8,
+    /// The status code.
+    pub code: u16,
+    /// The reason phrase, which may be empty.
+    pub reason: Bytes,
+    /// The headers, in the order they were received.
+    pub headers: Vec<Header>,
+}
+
+impl ResponseHead {
+    /// Returns the value of the first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&Bytes> {
+        find_header(&self.headers, name)
+    }
+
+    fn from_spans(head: &Bytes, spans: Spans) -> Self {
+        let headers = spans.to_headers(head);
+        let [reason, _] = spans.start_line;
+        Self {
+            minor_version: spans.minor_version,
+            code: spans.code,
+            reason: head.slice(reason),
+            headers,
+        }
+    }
+}
+
+fn find_header<'a>(headers: &'a [Header], name: &str) -> Option<&'a Bytes> {
+    headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case(name.as_bytes()))
+        .map(|header| &header.value)
+}
+
+/// A head parsed in the buffer of the reader.
+///
+/// The parts are stored as ranges of the head, so they can be sliced from it
+/// once it is split from the buffer without parsing it again.
+struct Spans {
+    minor_version: u8,
+    code: u16,
+    // The method and the path of a request, or the reason of a response
+    start_line: [Range<usize>; 2],
+    headers: Vec<[Range<usize>; 2]>,
+}
+
+impl Spans {
+    fn new(head: &[u8], minor_version: u8, code: u16, start_line: [&[u8]; 2]) -> Self {
+        Self {
+            minor_version,
+            code,
+            start_line: start_line.map(|part| span(head, part)),
+            headers: Vec::new(),
+        }
+    }
+
+    fn with_headers(mut self, head: &[u8], headers: &[httparse::Header<'_>]) -> Self {
+        self.headers = headers
+            .iter()
+            .map(|header| [span(head, header.name.as_bytes()), span(head, header.value)])
+            .collect();
+        self
+    }
+
+    fn to_headers(&self, head: &Bytes) -> Vec<Header> {
+        self.headers
+            .iter()
+            .map(|[name, value]| Header {
+                name: head.slice(name.clone()),
+                value: head.slice(value.clone()),
+            })
+            .collect()
+    }
+}
+
+/// Returns the range of `part`, a subslice of `head`.
+fn span(head: &[u8], part: &[u8]) -> Range<usize> {
+    if part.is_empty() {
+        return 0..0;
+    }
+    let start = part.as_ptr() as usize - head.as_ptr() as usize;
+    start..start + part.len()
+}
+
+fn parse_request<'b>(
+    head: &'b [u8],
+    headers: &mut [httparse::Header<'b>],
+) -> Result<Spans, ParseError> {
+    let mut request = httparse::Request::new(headers);
+    require_complete(request.parse(head)?)?;
+    let start_line = [
+        request.method.unwrap_or_default().as_bytes(),
+        request.path.unwrap_or_default().as_bytes(),
+    ];
+    let spans = Spans::new(head, request.version.unwrap_or_default(), 0, start_line);
+    Ok(spans.with_headers(head, request.headers))
+}
+
+fn parse_response<'b>(
+    head: &'b [u8],
+    headers: &mut [httparse::Header<'b>],
+) -> Result<Spans, ParseError> {
+    let mut response = httparse::Response::new(headers);
+    require_complete(response.parse(head)?)?;
+    let start_line = [response.reason.unwrap_or_default().as_bytes(), &[]];
+    let spans = Spans::new(
+        head,
+        response.version.unwrap_or_default(),
+        response.code.unwrap_or_default(),
+        start_line,
+    );
+    Ok(spans.with_headers(head, response.headers))
+}
+
+fn require_complete(status: Status<usize>) -> Result<(), ParseError> {
+    match status {
+        Status::Complete(_) => Ok(()),
+        // The head ends with an empty line, so it only misses its start line
+        // when it begins with empty lines
+        Status::Partial => Err(ParseError::NewLine),
+    }
+}
+
+/// Splits the next head from the reader once its empty line was found.
+///
+/// The buffer is scanned for the end of the head incrementally, using the
+/// `scanned` cursor, then the head is parsed once.
+fn poll_read_head<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    scanned: &mut usize,
+    limits: HeadLimits,
+    parse: for<'b> fn(&'b [u8], &mut [httparse::Header<'b>]) -> Result<Spans, ParseError>,
+) -> Poll<io::Result<(Bytes, Spans)>>
+where
+    R: AsyncBufRead + ?Sized,
+{
+    let Some(head_len) = ready!(poll_scan(
+        reader.as_mut(),
+        cx,
+        b"\r\n\r\n",
+        scanned,
+        limits.max_size
+    ))?
+    else {
+        return Poll::Ready(Err(eof_error(reader.as_ref())));
+    };
+
+    // Parse the head in the buffer, so no data is consumed on errors
+    let mut headers = vec![httparse::EMPTY_HEADER; limits.max_headers];
+    let spans = match parse(&reader.as_ref().buf()[..head_len], &mut headers) {
+        Ok(spans) => spans,
+        Err(ParseError::TooManyHeaders) => {
+            return Poll::Ready(Err(LimitExceeded {
+                limit: limits.max_headers,
+            }
+            .into()));
+        }
+        Err(err) => return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, err))),
+    };
+    Poll::Ready(Ok((reader.split_to(head_len), spans)))
+}
+
+pub(crate) fn read_request_head<R>(reader: &mut R, limits: HeadLimits) -> ReadRequestHead<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    ReadRequestHead {
+        reader,
+        limits,
+        scanned: 0,
+    }
+}
+
+pub(crate) fn read_response_head<R>(reader: &mut R, limits: HeadLimits) -> ReadResponseHead<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    ReadResponseHead {
+        reader,
+        limits,
+        scanned: 0,
+    }
+}
+
+/// Future for the [`read_request_head`](crate::AsyncBufReadExt::read_request_head) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadRequestHead<'a, R: ?Sized> {
+    reader: &'a mut R,
+    limits: HeadLimits,
+    scanned: usize,
+}
+
+impl<R> Future for ReadRequestHead<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    type Output = io::Result<RequestHead>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.get_mut();
+        let reader = Pin::new(&mut *me.reader);
+        let (head, spans) = ready!(poll_read_head(
+            reader,
+            cx,
+            &mut me.scanned,
+            me.limits,
+            parse_request
+        ))?;
+        Poll::Ready(Ok(RequestHead::from_spans(&head, spans)))
+    }
+}
+
+/// Future for the [`read_response_head`](crate::AsyncBufReadExt::read_response_head) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadResponseHead<'a, R: ?Sized> {
+    reader: &'a mut R,
+    limits: HeadLimits,
+    scanned: usize,
+}
+
+impl<R> Future for ReadResponseHead<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    type Output = io::Result<ResponseHead>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.get_mut();
+        let reader = Pin::new(&mut *me.reader);
+        let (head, spans) = ready!(poll_read_head(
+            reader,
+            cx,
+            &mut me.scanned,
+            me.limits,
+            parse_response
+        ))?;
+        Poll::Ready(Ok(ResponseHead::from_spans(&head, spans)))
+    }
+}
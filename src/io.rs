@@ -1,4 +1,199 @@
 pub use std::io::Result;
 
 #[cfg(feature = "tokio")]
-pub(crate) use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+pub(crate) use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+pub(crate) use self::read_buf::ReadBuf;
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+pub(crate) use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+
+#[cfg(not(any(feature = "tokio", feature = "futures")))]
+compile_error!("either the `tokio` or the `futures` feature must be enabled");
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Reads from the runtime reader into a [`ReadBuf`].
+///
+/// This hides the difference between the `tokio` and `futures` read signatures.
+#[cfg(feature = "tokio")]
+pub(crate) fn poll_read_buf<R: AsyncRead + ?Sized>(
+    reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+) -> Poll<Result<()>> {
+    reader.poll_read(cx, buf)
+}
+
+/// Reads from the runtime reader into a [`ReadBuf`].
+///
+/// This hides the difference between the `tokio` and `futures` read signatures.
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+pub(crate) fn poll_read_buf<R: AsyncRead + ?Sized>(
+    reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+) -> Poll<Result<()>> {
+    let n = std::task::ready!(reader.poll_read(cx, buf.initialize_unfilled()))?;
+    buf.advance(n);
+    Poll::Ready(Ok(()))
+}
+
+/// Reads at most `limit` bytes from the runtime reader into a [`ReadBuf`].
+#[cfg(feature = "tokio")]
+pub(crate) fn poll_read_buf_limited<R: AsyncRead + ?Sized>(
+    reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+    limit: usize,
+) -> Poll<Result<()>> {
+    let limit = std::cmp::min(limit, buf.remaining());
+    let mut limited = ReadBuf::new(buf.initialize_unfilled_to(limit));
+    std::task::ready!(reader.poll_read(cx, &mut limited))?;
+    let n = limited.filled().len();
+    buf.advance(n);
+    Poll::Ready(Ok(()))
+}
+
+/// Reads at most `limit` bytes from the runtime reader into a [`ReadBuf`].
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+pub(crate) fn poll_read_buf_limited<R: AsyncRead + ?Sized>(
+    reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+    limit: usize,
+) -> Poll<Result<()>> {
+    let limit = std::cmp::min(limit, buf.remaining());
+    let n = std::task::ready!(reader.poll_read(cx, &mut buf.initialize_unfilled()[..limit]))?;
+    buf.advance(n);
+    Poll::Ready(Ok(()))
+}
+
+/// Implements the runtime `AsyncRead` trait for a type that provides an
+/// inherent `poll_read_buf` method.
+///
+/// This allows readers to be written once against [`ReadBuf`] while still
+/// exposing the signature expected by the selected runtime.
+macro_rules! impl_async_read {
+    (impl<$($generics:ident),*> for $ty:ty $(where $($bounds:tt)*)?) => {
+        #[cfg(feature = "tokio")]
+        impl<$($generics),*> $crate::io::AsyncRead for $ty $(where $($bounds)*)? {
+            fn poll_read(
+                self: ::std::pin::Pin<&mut Self>,
+                cx: &mut ::std::task::Context<'_>,
+                buf: &mut $crate::io::ReadBuf<'_>,
+            ) -> ::std::task::Poll<::std::io::Result<()>> {
+                Self::poll_read_buf(self, cx, buf)
+            }
+        }
+
+        #[cfg(all(feature = "futures", not(feature = "tokio")))]
+        impl<$($generics),*> $crate::io::AsyncRead for $ty $(where $($bounds)*)? {
+            fn poll_read(
+                self: ::std::pin::Pin<&mut Self>,
+                cx: &mut ::std::task::Context<'_>,
+                buf: &mut [u8],
+            ) -> ::std::task::Poll<::std::io::Result<usize>> {
+                let mut buf = $crate::io::ReadBuf::new(buf);
+                ::std::task::ready!(Self::poll_read_buf(self, cx, &mut buf))?;
+                ::std::task::Poll::Ready(Ok(buf.filled().len()))
+            }
+        }
+    };
+}
+
+pub(crate) use impl_async_read;
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+mod read_buf {
+    use std::mem::MaybeUninit;
+
+    /// A minimal equivalent of tokio's `ReadBuf` used by the `futures` backend.
+    ///
+    /// It wraps a possibly uninitialized buffer and keeps track of the filled
+    /// and initialized regions.
+    pub(crate) struct ReadBuf<'a> {
+        buf: &'a mut [MaybeUninit<u8>],
+        filled: usize,
+        initialized: usize,
+    }
+
+    impl<'a> ReadBuf<'a> {
+        /// Creates a new `ReadBuf` from a fully initialized buffer.
+        pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+            let initialized = buf.len();
+            // SAFETY: An initialized buffer is a valid uninitialized buffer and
+            // we never write uninitialized bytes into it.
+            let buf = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+            Self {
+                buf,
+                filled: 0,
+                initialized,
+            }
+        }
+
+        /// Creates a new `ReadBuf` from a buffer that may be uninitialized.
+        pub(crate) fn uninit(buf: &'a mut [MaybeUninit<u8>]) -> Self {
+            Self {
+                buf,
+                filled: 0,
+                initialized: 0,
+            }
+        }
+
+        /// Returns the filled part of the buffer.
+        pub(crate) fn filled(&self) -> &[u8] {
+            // SAFETY: The filled region is always initialized.
+            unsafe { std::slice::from_raw_parts(self.buf.as_ptr().cast(), self.filled) }
+        }
+
+        /// Returns the number of bytes that can still be written.
+        pub(crate) fn remaining(&self) -> usize {
+            self.buf.len() - self.filled
+        }
+
+        /// Initializes the unfilled part of the buffer and returns it.
+        pub(crate) fn initialize_unfilled(&mut self) -> &mut [u8] {
+            for byte in &mut self.buf[self.initialized..] {
+                byte.write(0);
+            }
+            self.initialized = self.buf.len();
+            let unfilled = &mut self.buf[self.filled..];
+            // SAFETY: The whole buffer was initialized above.
+            unsafe { std::slice::from_raw_parts_mut(unfilled.as_mut_ptr().cast(), unfilled.len()) }
+        }
+
+        /// Marks `n` more bytes as filled.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the filled region would go past the initialized region.
+        pub(crate) fn advance(&mut self, n: usize) {
+            let filled = self.filled.checked_add(n).expect("filled overflow");
+            assert!(
+                filled <= self.initialized,
+                "filled must not exceed initialized"
+            );
+            self.filled = filled;
+        }
+
+        /// Appends data to the buffer, advancing the filled region.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `self.remaining()` is less than `src.len()`.
+        pub(crate) fn put_slice(&mut self, src: &[u8]) {
+            assert!(
+                self.remaining() >= src.len(),
+                "src.len() must fit in remaining()"
+            );
+            let end = self.filled + src.len();
+            // SAFETY: `MaybeUninit<u8>` has the same layout as `u8`.
+            let src = unsafe { &*(src as *const [u8] as *const [MaybeUninit<u8>]) };
+            self.buf[self.filled..end].copy_from_slice(src);
+            self.filled = end;
+            self.initialized = std::cmp::max(self.initialized, end);
+        }
+    }
+}
@@ -1,22 +1,70 @@
 use std::ops::DerefMut;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::task::{Context, Poll, ready};
+
+use bytes::Bytes;
 
 pub use self::buf_read_ext::AsyncBufReadExt;
-pub use self::buf_reader::AsyncBufReader;
+pub use self::buf_reader::{AsyncBufReader, Checkpoint, ReaderParts};
+pub use self::buf_stream::AsyncBufStream;
+pub use self::buf_writer::AsyncBufWriter;
+pub use self::chain::Chain;
+pub use self::chunked::ChunkedDecoder;
+pub use self::error::{BufferOverflow, ChunkedError, LimitExceeded, UnexpectedEof};
+pub use self::memory::InMemory;
 pub use self::passthrough::AsyncBufPassthrough;
+pub use self::prefixed::Prefixed;
+pub use self::split::{LineEnding, Lines, Split};
+pub use self::take::Take;
+#[cfg(feature = "time")]
+pub use self::throttle::Throttled;
 
 mod buf_read_ext;
 mod buf_reader;
+mod buf_stream;
+mod buf_writer;
+mod chain;
+mod chunked;
+#[cfg(feature = "codec")]
+pub mod codec;
+pub mod compat;
+#[cfg(any(
+    feature = "compression-gzip",
+    feature = "compression-deflate",
+    feature = "compression-brotli",
+    feature = "compression-zstd"
+))]
+pub mod compression;
+mod error;
+pub mod framing;
+#[cfg(feature = "http")]
+pub mod http;
 mod io;
+mod memory;
 mod passthrough;
 mod peek;
+mod prefixed;
+pub mod proxy;
+mod read_bytes;
+mod scan;
+pub mod sniff;
+mod split;
+mod take;
+pub mod tee;
+#[cfg(feature = "time")]
+mod throttle;
+#[cfg(feature = "time")]
+pub mod time;
 
 /// Reads bytes asynchronously and buffers them.
 ///
 /// Utilities for working with `AsyncBufRead` values are provided by
 /// [`AsyncBufReadExt`].
 ///
+/// In-memory sources like `&[u8]` and [`Cursor`](std::io::Cursor) implement it
+/// directly, [`Bytes`] and [`VecDeque<u8>`](std::collections::VecDeque) can be
+/// read through an [`InMemory`] wrapper.
+///
 /// [`AsyncBufReadExt`]: crate::AsyncBufReadExt
 pub trait AsyncBufRead: io::AsyncRead {
     /// Returns true if the inner reader has reached EOF.
@@ -36,14 +84,13 @@ pub trait AsyncBufRead: io::AsyncRead {
     /// readable or is closed.
     ///
     /// This function doesn't consume the data, it only returns a slice up
-    /// to the requested amount. This means that subsequent calls to [`poll_read`]
+    /// to the requested amount. This means that subsequent calls to `poll_read`
     /// will return the same contents. As such, [`consume`] can be called
     /// with the number of bytes that are consumed from this buffer to
-    /// ensure that the bytes are not returned by [`poll_read`].
+    /// ensure that the bytes are not returned by `poll_read`.
     ///
     /// To check if the inner reader has reached EOF, use [`eof`].
     ///
-    /// [`poll_read`]: AsyncRead::poll_read
     /// [`consume`]: AsyncBufRead::consume
     /// [`eof`]: AsyncBufRead::eof
     fn poll_fill_buf<'a>(
@@ -52,8 +99,48 @@ pub trait AsyncBufRead: io::AsyncRead {
         amt: usize,
     ) -> Poll<io::Result<&'a [u8]>>;
 
+    /// Attempts to return exactly `amt` bytes of the internal buffer, filling it
+    /// with more data from the inner reader until enough data is available.
+    ///
+    /// Unlike [`poll_fill_buf`], this function keeps reading from the inner reader
+    /// until the buffer holds at least `amt` bytes.
+    ///
+    /// If the inner reader reaches EOF before that, an error of kind
+    /// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) wrapping
+    /// [`crate::UnexpectedEof`] is returned. If the buffer stops growing before
+    /// EOF, for example in passthrough mode, an error of kind
+    /// [`Other`](std::io::ErrorKind::Other) is returned instead. In both cases,
+    /// the partial data stays in the buffer.
+    ///
+    /// [`poll_fill_buf`]: AsyncBufRead::poll_fill_buf
+    fn poll_fill_buf_exact<'a>(
+        mut self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        loop {
+            let before = self.as_ref().buf().len();
+            let filled = ready!(self.as_mut().poll_fill_buf(cx, amt))?.len();
+            if filled >= amt {
+                return self.poll_fill_buf(cx, amt);
+            }
+            if self.as_ref().eof() {
+                return Poll::Ready(Err(UnexpectedEof {
+                    requested: amt,
+                    partial: filled,
+                }
+                .into()));
+            }
+            if self.as_ref().buf().len() <= before {
+                return Poll::Ready(Err(std::io::Error::other(
+                    "the reader stopped buffering data before EOF",
+                )));
+            }
+        }
+    }
+
     /// Tells this buffer that `amt` bytes have been consumed from the buffer,
-    /// so they should no longer be returned in calls to [`poll_read`].
+    /// so they should no longer be returned in calls to `poll_read`.
     ///
     /// This function is a lower-level call. It needs to be paired with the
     /// [`poll_fill_buf`] method to function properly. This function does
@@ -65,9 +152,25 @@ pub trait AsyncBufRead: io::AsyncRead {
     /// The `amt` must be `<=` the number of bytes in the buffer returned by
     /// [`poll_fill_buf`].
     ///
-    /// [`poll_read`]: AsyncRead::poll_read
     /// [`poll_fill_buf`]: AsyncBufRead::poll_fill_buf
     fn consume(self: Pin<&mut Self>, amt: usize);
+
+    /// Removes the first `amt` bytes from the internal buffer and returns them.
+    ///
+    /// This is equivalent to [`consume`], but the consumed data is returned as
+    /// an owned [`Bytes`]. Implementations backed by a [`BytesMut`](bytes::BytesMut)
+    /// can split their buffer instead of copying the data.
+    ///
+    /// The `amt` must be `<=` the number of bytes in the buffer returned by
+    /// [`poll_fill_buf`].
+    ///
+    /// [`consume`]: AsyncBufRead::consume
+    /// [`poll_fill_buf`]: AsyncBufRead::poll_fill_buf
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        let bytes = Bytes::copy_from_slice(&self.as_ref().buf()[..amt]);
+        self.consume(amt);
+        bytes
+    }
 }
 
 macro_rules! deref_async_buf_read {
@@ -88,9 +191,21 @@ macro_rules! deref_async_buf_read {
             Pin::new(&mut **self.get_mut()).poll_fill_buf(cx, amt)
         }
 
+        fn poll_fill_buf_exact(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            amt: usize,
+        ) -> Poll<io::Result<&[u8]>> {
+            Pin::new(&mut **self.get_mut()).poll_fill_buf_exact(cx, amt)
+        }
+
         fn consume(mut self: Pin<&mut Self>, amt: usize) {
             Pin::new(&mut **self).consume(amt)
         }
+
+        fn split_to(mut self: Pin<&mut Self>, amt: usize) -> Bytes {
+            Pin::new(&mut **self).split_to(amt)
+        }
     };
 }
 
@@ -123,14 +238,26 @@ where
         self.get_mut().as_mut().poll_fill_buf(cx, amt)
     }
 
+    fn poll_fill_buf_exact(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        self.get_mut().as_mut().poll_fill_buf_exact(cx, amt)
+    }
+
     fn consume(self: Pin<&mut Self>, amt: usize) {
         self.get_mut().as_mut().consume(amt);
     }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        self.get_mut().as_mut().split_to(amt)
+    }
 }
 
 impl AsyncBufRead for &[u8] {
     fn eof(self: Pin<&Self>) -> bool {
-        false
+        self.is_empty()
     }
 
     fn buf(self: Pin<&Self>) -> &[u8] {
@@ -146,7 +273,70 @@ impl AsyncBufRead for &[u8] {
         Poll::Ready(Ok(&self[..amt]))
     }
 
+    fn poll_fill_buf_exact(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        Poll::Ready(fill_exact(self.get_mut(), amt))
+    }
+
     fn consume(mut self: Pin<&mut Self>, amt: usize) {
         *self = &self[amt..];
     }
 }
+
+/// Only available with the `tokio` backend, since `futures` doesn't implement
+/// its `AsyncRead` trait for `Cursor`.
+impl<T> AsyncBufRead for std::io::Cursor<T>
+where
+    T: AsRef<[u8]> + Unpin,
+    Self: io::AsyncRead,
+{
+    fn eof(self: Pin<&Self>) -> bool {
+        self.buf().is_empty()
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        let this = self.get_ref();
+        let inner = this.get_ref().as_ref();
+        let pos = std::cmp::min(this.position(), inner.len() as u64) as usize;
+        &inner[pos..]
+    }
+
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        let buf = self.into_ref().buf();
+        let amt = std::cmp::min(buf.len(), amt);
+        Poll::Ready(Ok(&buf[..amt]))
+    }
+
+    fn poll_fill_buf_exact(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        Poll::Ready(fill_exact(self.into_ref().buf(), amt))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.set_position(this.position() + amt as u64);
+    }
+}
+
+/// Returns the first `amt` bytes of a source that holds all of its data in
+/// memory, failing if it doesn't have enough.
+pub(crate) fn fill_exact(buf: &[u8], amt: usize) -> std::io::Result<&[u8]> {
+    match buf.get(..amt) {
+        Some(buf) => Ok(buf),
+        None => Err(UnexpectedEof {
+            requested: amt,
+            partial: buf.len(),
+        }
+        .into()),
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Buf, Bytes};
+
+use crate::io::{self as rt, ReadBuf};
+use crate::{AsyncBufRead, fill_exact};
+
+/// An in-memory reader over [`Bytes`] or a [`VecDeque<u8>`].
+///
+/// These types don't implement the runtime `AsyncRead` trait required by
+/// [`AsyncBufRead`], so they are wrapped in this type. Like `&[u8]`, the
+/// reader reports EOF once all the data is consumed.
+///
+/// For a `VecDeque`, the data is made contiguous when filling the buffer, so
+/// [`buf`](AsyncBufRead::buf) might only return part of the data before that.
+#[derive(Debug, Clone, Default)]
+pub struct InMemory<B> {
+    inner: B,
+}
+
+impl<B> InMemory<B> {
+    /// Creates a new `InMemory` reading from `inner`.
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+
+    /// Gets a reference to the underlying data.
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying data.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    /// Consumes this `InMemory`, returning the data that was not read.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl InMemory<Bytes> {
+    fn poll_read_buf(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let inner = &mut self.get_mut().inner;
+        let amt = std::cmp::min(inner.len(), buf.remaining());
+        buf.put_slice(&inner[..amt]);
+        inner.advance(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<> for InMemory<Bytes>);
+
+impl AsyncBufRead for InMemory<Bytes> {
+    fn eof(self: Pin<&Self>) -> bool {
+        self.inner.is_empty()
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        &self.get_ref().inner
+    }
+
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        let inner = &self.get_mut().inner;
+        let amt = std::cmp::min(inner.len(), amt);
+        Poll::Ready(Ok(&inner[..amt]))
+    }
+
+    fn poll_fill_buf_exact(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        Poll::Ready(fill_exact(&self.get_mut().inner, amt))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().inner.advance(amt);
+    }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        self.get_mut().inner.split_to(amt)
+    }
+}
+
+impl InMemory<VecDeque<u8>> {
+    fn poll_read_buf(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let inner = &mut self.get_mut().inner;
+        let front = inner.as_slices().0;
+        let amt = std::cmp::min(front.len(), buf.remaining());
+        buf.put_slice(&front[..amt]);
+        inner.drain(..amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<> for InMemory<VecDeque<u8>>);
+
+impl AsyncBufRead for InMemory<VecDeque<u8>> {
+    fn eof(self: Pin<&Self>) -> bool {
+        self.inner.is_empty()
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        self.get_ref().inner.as_slices().0
+    }
+
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        let buf = self.get_mut().inner.make_contiguous();
+        let amt = std::cmp::min(buf.len(), amt);
+        Poll::Ready(Ok(&buf[..amt]))
+    }
+
+    fn poll_fill_buf_exact(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        Poll::Ready(fill_exact(self.get_mut().inner.make_contiguous(), amt))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().inner.drain(..amt);
+    }
+}
@@ -15,6 +15,12 @@ pub trait AsyncBufPassthrough {
     fn passthrough(&mut self, enabled: bool);
 }
 
+impl<T: AsyncBufPassthrough + ?Sized> AsyncBufPassthrough for &mut T {
+    fn passthrough(&mut self, enabled: bool) {
+        (**self).passthrough(enabled);
+    }
+}
+
 #[cfg(feature = "tokio-rustls")]
 impl<IO: AsyncBufPassthrough> AsyncBufPassthrough for tokio_rustls::server::TlsStream<IO> {
     fn passthrough(&mut self, enabled: bool) {
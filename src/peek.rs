@@ -7,6 +7,7 @@ use std::task::{Context, Poll};
 use pin_project_lite::pin_project;
 
 use crate::AsyncBufRead;
+use crate::scan::{eof_error, poll_scan};
 
 pub(crate) fn peek<'a, R>(reader: &'a mut R, amt: usize) -> Peek<'a, R>
 where
@@ -64,3 +65,111 @@ where
         }
     }
 }
+
+pub(crate) fn peek_exact<'a, R>(reader: &'a mut R, amt: usize) -> PeekExact<'a, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    PeekExact {
+        reader: Some(reader),
+        amt,
+        _pin: PhantomPinned,
+    }
+}
+
+pin_project! {
+    /// Future for the [`peek_exact`](crate::AsyncBufReadExt::peek_exact) method.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct PeekExact<'a, R: ?Sized> {
+        reader: Option<&'a mut R>,
+        amt: usize,
+        #[pin]
+        _pin: PhantomPinned,
+    }
+}
+
+impl<'a, R> Future for PeekExact<'a, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    type Output = io::Result<&'a [u8]>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.project();
+
+        let reader = me.reader.take().expect("Polled after completion.");
+        match Pin::new(&mut *reader).poll_fill_buf_exact(cx, *me.amt) {
+            Poll::Ready(Ok(slice)) => unsafe {
+                // SAFETY: See `Peek::poll`, the reader is `None` once we return.
+                let slice = std::mem::transmute::<&[u8], &'a [u8]>(slice);
+                Poll::Ready(Ok(slice))
+            },
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => {
+                *me.reader = Some(reader);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+pub(crate) fn peek_until<'a, R, D>(
+    reader: &'a mut R,
+    delimiter: D,
+    limit: usize,
+) -> PeekUntil<'a, R, D>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+    D: AsRef<[u8]>,
+{
+    PeekUntil {
+        reader: Some(reader),
+        delimiter,
+        limit,
+        scanned: 0,
+        _pin: PhantomPinned,
+    }
+}
+
+pin_project! {
+    /// Future for the [`peek_until`](crate::AsyncBufReadExt::peek_until) and
+    /// [`peek_line`](crate::AsyncBufReadExt::peek_line) methods.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct PeekUntil<'a, R: ?Sized, D> {
+        reader: Option<&'a mut R>,
+        delimiter: D,
+        limit: usize,
+        scanned: usize,
+        #[pin]
+        _pin: PhantomPinned,
+    }
+}
+
+impl<'a, R, D> Future for PeekUntil<'a, R, D>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+    D: AsRef<[u8]>,
+{
+    type Output = io::Result<&'a [u8]>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.project();
+
+        let reader = me.reader.take().expect("Polled after completion.");
+        let delimiter = me.delimiter.as_ref();
+        match poll_scan(Pin::new(&mut *reader), cx, delimiter, me.scanned, *me.limit) {
+            Poll::Ready(Ok(Some(end))) => {
+                let reader: &'a R = reader;
+                Poll::Ready(Ok(&Pin::new(reader).buf()[..end]))
+            }
+            Poll::Ready(Ok(None)) => Poll::Ready(Err(eof_error(Pin::new(&*reader)))),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => {
+                *me.reader = Some(reader);
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes};
+use pin_project_lite::pin_project;
+
+use crate::AsyncBufPassthrough;
+use crate::io::{self as rt, AsyncRead, AsyncWrite, ReadBuf};
+
+pin_project! {
+    /// The `Prefixed` struct returns a prefix before the data of a reader.
+    ///
+    /// It is used to hand the data that was already read from a stream, for
+    /// example while sniffing the protocol, to a new layer like a TLS acceptor
+    /// without losing any byte. Writes are forwarded to the inner stream.
+    #[derive(Debug)]
+    pub struct Prefixed<R> {
+        prefix: Bytes,
+        #[pin]
+        inner: R,
+    }
+}
+
+impl<R> Prefixed<R> {
+    /// Creates a new `Prefixed` that returns `prefix` before the data of `inner`.
+    pub fn new(prefix: impl Into<Bytes>, inner: R) -> Self {
+        Self {
+            prefix: prefix.into(),
+            inner,
+        }
+    }
+
+    /// Returns the part of the prefix that was not read yet.
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader before
+    /// the prefix was read.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader before
+    /// the prefix was read.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().inner
+    }
+
+    /// Consumes this `Prefixed`, returning the part of the prefix that was
+    /// not read yet and the underlying reader.
+    pub fn into_parts(self) -> (Bytes, R) {
+        (self.prefix, self.inner)
+    }
+}
+
+impl<R: AsyncRead> Prefixed<R> {
+    fn poll_read_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let me = self.project();
+        if me.prefix.is_empty() {
+            return rt::poll_read_buf(me.inner, cx, buf);
+        }
+        let amt = std::cmp::min(buf.remaining(), me.prefix.len());
+        buf.put_slice(&me.prefix[..amt]);
+        me.prefix.advance(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<R> for Prefixed<R> where R: AsyncRead);
+
+impl<R: AsyncBufPassthrough> AsyncBufPassthrough for Prefixed<R> {
+    fn passthrough(&mut self, enabled: bool) {
+        self.inner.passthrough(enabled);
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: AsyncWrite> AsyncWrite for Prefixed<R> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_pin_mut().poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.get_pin_mut().poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.get_ref().is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_pin_mut().poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_pin_mut().poll_shutdown(cx)
+    }
+}
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+impl<R: AsyncWrite> AsyncWrite for Prefixed<R> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_pin_mut().poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.get_pin_mut().poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_pin_mut().poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_pin_mut().poll_close(cx)
+    }
+}
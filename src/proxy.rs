@@ -0,0 +1,380 @@
+//! PROXY protocol header parsing.
+//!
+//! Load balancers like HAProxy can send a [PROXY protocol] header before the
+//! application data to forward the original addresses of the connection. The
+//! [`read_header`] function reads a v1 (text) or v2 (binary) header and
+//! removes it from the reader, leaving it positioned at the payload.
+//!
+//! [PROXY protocol]: https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::Bytes;
+
+use crate::AsyncBufRead;
+use crate::scan::{eof_error, poll_scan};
+use crate::sniff::{PROXY_V1_SIGNATURE, PROXY_V2_SIGNATURE};
+
+/// The maximum length of a v1 header, including the CRLF.
+const V1_MAX_LEN: usize = 107;
+
+/// The length of the fixed part of a v2 header.
+const V2_HEADER_LEN: usize = 16;
+
+/// A parsed PROXY protocol header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// The version of the protocol, either 1 or 2.
+    pub version: u8,
+    /// The command of the header.
+    pub command: ProxyCommand,
+    /// The transport protocol of the proxied connection.
+    pub transport: ProxyTransport,
+    /// The addresses of the proxied connection.
+    pub addresses: ProxyAddresses,
+    /// The Type-Length-Value vectors of a v2 header.
+    pub tlvs: Vec<Tlv>,
+}
+
+/// The command of a PROXY protocol header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyCommand {
+    /// The connection was established by the proxy itself, for example for
+    /// health checks. The addresses must be ignored.
+    Local,
+    /// The connection was proxied on behalf of a client.
+    Proxy,
+}
+
+/// The transport protocol of the proxied connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyTransport {
+    /// Unknown or unspecified.
+    Unspecified,
+    /// A stream protocol like TCP.
+    Stream,
+    /// A datagram protocol like UDP.
+    Datagram,
+}
+
+/// The addresses of the proxied connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProxyAddresses {
+    /// The addresses are unknown or must be ignored.
+    Unknown,
+    /// IPv4 or IPv6 addresses.
+    Inet {
+        /// The address of the client.
+        source: SocketAddr,
+        /// The address the client connected to.
+        destination: SocketAddr,
+    },
+    /// Unix socket paths, without the trailing NUL bytes.
+    Unix {
+        /// The path of the client.
+        source: Bytes,
+        /// The path the client connected to.
+        destination: Bytes,
+    },
+}
+
+/// A Type-Length-Value vector of a v2 header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlv {
+    /// The type of the vector, for example `0x01` for ALPN.
+    pub kind: u8,
+    /// The value of the vector.
+    pub value: Bytes,
+}
+
+/// The error returned when the data is not a valid PROXY protocol header.
+///
+/// It is wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidData`]
+/// and can be retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyError {
+    /// The data doesn't start with a PROXY protocol signature.
+    MissingSignature,
+    /// The header has the signature, but its content is invalid.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSignature => f.write_str("missing PROXY protocol signature"),
+            Self::Invalid(reason) => write!(f, "invalid PROXY protocol header, {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ProxyError {}
+
+impl From<ProxyError> for io::Error {
+    fn from(err: ProxyError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Reads a PROXY protocol header and removes it from the reader.
+///
+/// Both the v1 (text) and v2 (binary) formats are supported. On success, the
+/// reader is positioned at the first byte of the application payload.
+///
+/// Equivalent to:
+///
+/// ```ignore
+/// async fn read_header<R>(reader: &mut R) -> io::Result<ProxyHeader>;
+/// ```
+///
+/// # Errors
+///
+/// This function will return an I/O error if the underlying reader was
+/// read, but returned an error.
+///
+/// If the data is not a valid header, an error wrapping a [`ProxyError`] is
+/// returned. If the reader reaches EOF before the end of the header, an error
+/// wrapping [`UnexpectedEof`](crate::UnexpectedEof) is returned. In both
+/// cases no data was consumed.
+///
+/// # Cancel safety
+///
+/// This method is cancel safe. If you use it as the event in a
+/// `select!` statement and some other branch completes first,
+/// then it is guaranteed that no data was read.
+pub fn read_header<R>(reader: &mut R) -> ReadHeader<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    ReadHeader { reader, scanned: 0 }
+}
+
+/// Future for the [`read_header`] function.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadHeader<'a, R: ?Sized> {
+    reader: &'a mut R,
+    scanned: usize,
+}
+
+impl<R> Future for ReadHeader<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    type Output = io::Result<ProxyHeader>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.get_mut();
+        let mut reader = Pin::new(&mut *me.reader);
+
+        // Read until the signature can be confirmed or rejected
+        let version = loop {
+            let buf = reader.as_ref().buf();
+            let len = buf.len();
+            if buf.starts_with(PROXY_V1_SIGNATURE) {
+                break 1;
+            }
+            if buf.starts_with(PROXY_V2_SIGNATURE) {
+                break 2;
+            }
+            if !PROXY_V1_SIGNATURE.starts_with(&buf[..len.min(PROXY_V1_SIGNATURE.len())])
+                && !PROXY_V2_SIGNATURE.starts_with(&buf[..len.min(PROXY_V2_SIGNATURE.len())])
+            {
+                return Poll::Ready(Err(ProxyError::MissingSignature.into()));
+            }
+            ready!(reader.as_mut().poll_fill_buf_exact(cx, len + 1))?;
+        };
+
+        let header = if version == 1 {
+            let end = match ready!(poll_scan(
+                reader.as_mut(),
+                cx,
+                b"\r\n",
+                &mut me.scanned,
+                V1_MAX_LEN
+            )) {
+                Ok(Some(end)) => end,
+                Ok(None) => return Poll::Ready(Err(eof_error(reader.as_ref()))),
+                Err(err) => return Poll::Ready(Err(err)),
+            };
+            let header = parse_v1(&reader.as_ref().buf()[..end])?;
+            reader.consume(end);
+            header
+        } else {
+            let buf = ready!(reader.as_mut().poll_fill_buf_exact(cx, V2_HEADER_LEN))?;
+            let len = V2_HEADER_LEN + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+            ready!(reader.as_mut().poll_fill_buf_exact(cx, len))?;
+            let header = parse_v2(&reader.as_ref().buf()[..len])?;
+            parse_v2_owned(header, reader.split_to(len))
+        };
+        Poll::Ready(Ok(header))
+    }
+}
+
+fn parse_v1(line: &[u8]) -> Result<ProxyHeader, ProxyError> {
+    let line = std::str::from_utf8(&line[PROXY_V1_SIGNATURE.len()..line.len() - 2])
+        .map_err(|_| ProxyError::Invalid("header is not valid ASCII"))?;
+    let mut parts = line.split(' ');
+    let family = parts.next().unwrap_or_default();
+    let mut header = ProxyHeader {
+        version: 1,
+        command: ProxyCommand::Proxy,
+        transport: ProxyTransport::Unspecified,
+        addresses: ProxyAddresses::Unknown,
+        tlvs: Vec::new(),
+    };
+    // The rest of the line must be ignored for unknown connections
+    if family == "UNKNOWN" {
+        return Ok(header);
+    }
+
+    let (source, destination, source_port, destination_port) =
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(a), Some(b), Some(c), Some(d)) if parts.next().is_none() => (a, b, c, d),
+            _ => return Err(ProxyError::Invalid("wrong number of fields")),
+        };
+    let parse_ip = |ip: &str| -> Result<IpAddr, ProxyError> {
+        match family {
+            "TCP4" => ip.parse::<Ipv4Addr>().map(IpAddr::V4),
+            "TCP6" => ip.parse::<Ipv6Addr>().map(IpAddr::V6),
+            _ => return Err(ProxyError::Invalid("unsupported address family")),
+        }
+        .map_err(|_| ProxyError::Invalid("invalid address"))
+    };
+    let parse_port = |port: &str| -> Result<u16, ProxyError> {
+        // Leading zeros are not allowed
+        if port.starts_with('0') && port != "0" {
+            return Err(ProxyError::Invalid("invalid port"));
+        }
+        port.parse()
+            .map_err(|_| ProxyError::Invalid("invalid port"))
+    };
+    header.transport = ProxyTransport::Stream;
+    header.addresses = ProxyAddresses::Inet {
+        source: SocketAddr::new(parse_ip(source)?, parse_port(source_port)?),
+        destination: SocketAddr::new(parse_ip(destination)?, parse_port(destination_port)?),
+    };
+    Ok(header)
+}
+
+/// The ranges of a v2 header, resolved into owned data by [`parse_v2_owned`].
+struct V2Header {
+    command: ProxyCommand,
+    transport: ProxyTransport,
+    family: u8,
+    addresses: std::ops::Range<usize>,
+    tlvs: Vec<(u8, std::ops::Range<usize>)>,
+}
+
+fn parse_v2(buf: &[u8]) -> Result<V2Header, ProxyError> {
+    let version_command = buf[12];
+    if version_command >> 4 != 2 {
+        return Err(ProxyError::Invalid("unsupported version"));
+    }
+    let command = match version_command & 0x0f {
+        0x0 => ProxyCommand::Local,
+        0x1 => ProxyCommand::Proxy,
+        _ => return Err(ProxyError::Invalid("unsupported command")),
+    };
+    let family = buf[13] >> 4;
+    let transport = match buf[13] & 0x0f {
+        0x0 => ProxyTransport::Unspecified,
+        0x1 => ProxyTransport::Stream,
+        0x2 => ProxyTransport::Datagram,
+        _ => return Err(ProxyError::Invalid("unsupported transport protocol")),
+    };
+    let addresses_len = match family {
+        0x0 => 0,
+        0x1 => 12,
+        0x2 => 36,
+        0x3 => 216,
+        _ => return Err(ProxyError::Invalid("unsupported address family")),
+    };
+    let addresses = V2_HEADER_LEN..V2_HEADER_LEN + addresses_len;
+    if addresses.end > buf.len() {
+        return Err(ProxyError::Invalid("addresses don't fit in the header"));
+    }
+
+    let mut tlvs = Vec::new();
+    let mut pos = addresses.end;
+    while pos < buf.len() {
+        if pos + 3 > buf.len() {
+            return Err(ProxyError::Invalid("truncated TLV"));
+        }
+        let kind = buf[pos];
+        let len = u16::from_be_bytes([buf[pos + 1], buf[pos + 2]]) as usize;
+        let value = pos + 3..pos + 3 + len;
+        if value.end > buf.len() {
+            return Err(ProxyError::Invalid("truncated TLV"));
+        }
+        pos = value.end;
+        tlvs.push((kind, value));
+    }
+
+    Ok(V2Header {
+        command,
+        transport,
+        family,
+        addresses,
+        tlvs,
+    })
+}
+
+fn parse_v2_owned(header: V2Header, buf: Bytes) -> ProxyHeader {
+    let addresses = &buf[header.addresses.clone()];
+    let addresses = match (header.command, header.family) {
+        (ProxyCommand::Local, _) | (_, 0x0) => ProxyAddresses::Unknown,
+        (_, 0x1) => {
+            let ip = |at: usize| -> IpAddr {
+                Ipv4Addr::from(<[u8; 4]>::try_from(&addresses[at..at + 4]).unwrap()).into()
+            };
+            let port = |at: usize| u16::from_be_bytes([addresses[at], addresses[at + 1]]);
+            ProxyAddresses::Inet {
+                source: SocketAddr::new(ip(0), port(8)),
+                destination: SocketAddr::new(ip(4), port(10)),
+            }
+        }
+        (_, 0x2) => {
+            let ip = |at: usize| -> IpAddr {
+                Ipv6Addr::from(<[u8; 16]>::try_from(&addresses[at..at + 16]).unwrap()).into()
+            };
+            let port = |at: usize| u16::from_be_bytes([addresses[at], addresses[at + 1]]);
+            ProxyAddresses::Inet {
+                source: SocketAddr::new(ip(0), port(32)),
+                destination: SocketAddr::new(ip(16), port(34)),
+            }
+        }
+        _ => {
+            let path = |at: usize| {
+                let path = &addresses[at..at + 108];
+                let len = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+                let start = header.addresses.start + at;
+                buf.slice(start..start + len)
+            };
+            ProxyAddresses::Unix {
+                source: path(0),
+                destination: path(108),
+            }
+        }
+    };
+
+    ProxyHeader {
+        version: 2,
+        command: header.command,
+        transport: header.transport,
+        addresses,
+        tlvs: header
+            .tlvs
+            .into_iter()
+            .map(|(kind, value)| Tlv {
+                kind,
+                value: buf.slice(value),
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,37 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::Bytes;
+
+use crate::AsyncBufRead;
+
+pub(crate) fn read_bytes<R>(reader: &mut R, amt: usize) -> ReadBytes<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    ReadBytes { reader, amt }
+}
+
+/// Future for the [`read_bytes`](crate::AsyncBufReadExt::read_bytes) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadBytes<'a, R: ?Sized> {
+    reader: &'a mut R,
+    amt: usize,
+}
+
+impl<R> Future for ReadBytes<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    type Output = io::Result<Bytes>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.get_mut();
+        let mut reader = Pin::new(&mut *me.reader);
+        ready!(reader.as_mut().poll_fill_buf_exact(cx, me.amt))?;
+        Poll::Ready(Ok(reader.split_to(me.amt)))
+    }
+}
@@ -0,0 +1,121 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use crate::{AsyncBufRead, LimitExceeded, UnexpectedEof};
+
+/// Returns the position of the first occurrence of `needle` in `haystack`.
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    match needle {
+        [] => Some(0),
+        [byte] => haystack.iter().position(|b| b == byte),
+        _ => haystack
+            .windows(needle.len())
+            .position(|window| window == needle),
+    }
+}
+
+/// Fills the buffer of the reader until it contains the `delimiter`.
+///
+/// The `scanned` cursor keeps track of how much of the buffer was already
+/// searched so that the same bytes are not scanned twice across polls. It
+/// must start at zero and not be modified by the caller in between polls.
+///
+/// On success, returns the length of the buffer up to and including the
+/// delimiter, or `None` if the reader reached EOF before the delimiter, see
+/// [`eof_error`] for the matching error.
+/// An error wrapping [`LimitExceeded`] is returned if the delimiter is not
+/// found within the first `limit` bytes, see [`poll_grow`] for a buffer that
+/// stops growing before EOF.
+pub(crate) fn poll_scan<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    delimiter: &[u8],
+    scanned: &mut usize,
+    limit: usize,
+) -> Poll<io::Result<Option<usize>>>
+where
+    R: AsyncBufRead + ?Sized,
+{
+    loop {
+        let buf = reader.as_ref().buf();
+        let end = std::cmp::min(buf.len(), limit);
+
+        // Resume the search where it stopped, the delimiter might
+        // straddle the previously scanned bytes.
+        let start = scanned.saturating_sub(delimiter.len().saturating_sub(1));
+        if let Some(pos) = find(&buf[start..end], delimiter) {
+            return Poll::Ready(Ok(Some(start + pos + delimiter.len())));
+        }
+        *scanned = end;
+
+        if end >= limit {
+            return Poll::Ready(Err(LimitExceeded { limit }.into()));
+        }
+
+        if !ready!(poll_grow(reader.as_mut(), cx))? {
+            return Poll::Ready(Ok(None));
+        }
+    }
+}
+
+/// Returns the error for a delimiter that was not found before EOF.
+///
+/// At least one more byte was needed, in addition to the buffered data.
+pub(crate) fn eof_error<R>(reader: Pin<&R>) -> io::Error
+where
+    R: AsyncBufRead + ?Sized,
+{
+    let partial = reader.buf().len();
+    UnexpectedEof {
+        requested: partial + 1,
+        partial,
+    }
+    .into()
+}
+
+/// Fills the buffer of the reader with at least one more byte.
+///
+/// Returns `false` if the reader reached EOF. The decision is left to
+/// [`poll_fill_buf_exact`], so a buffer that stops growing before EOF, for
+/// example in passthrough mode, is an error rather than the end of the stream.
+///
+/// [`poll_fill_buf_exact`]: AsyncBufRead::poll_fill_buf_exact
+pub(crate) fn poll_grow<R>(mut reader: Pin<&mut R>, cx: &mut Context<'_>) -> Poll<io::Result<bool>>
+where
+    R: AsyncBufRead + ?Sized,
+{
+    let amt = reader.as_ref().buf().len() + 1;
+    match ready!(reader.as_mut().poll_fill_buf_exact(cx, amt)) {
+        Ok(_) => Poll::Ready(Ok(true)),
+        Err(err) if err.get_ref().is_some_and(|err| err.is::<UnexpectedEof>()) => {
+            Poll::Ready(Ok(false))
+        }
+        Err(err) => Poll::Ready(Err(err)),
+    }
+}
+
+/// Fills the buffer of the reader with at least one more byte, up to `amt`.
+///
+/// An error wrapping [`UnexpectedEof`] is returned if the reader reached EOF,
+/// see [`poll_grow`] for a buffer that stops growing before that.
+pub(crate) fn poll_fill_buf_more<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    amt: usize,
+) -> Poll<io::Result<()>>
+where
+    R: AsyncBufRead + ?Sized,
+{
+    let before = reader.as_ref().buf().len();
+    let amt = std::cmp::max(amt, before + 1);
+    ready!(reader.as_mut().poll_fill_buf(cx, amt))?;
+    if reader.as_ref().buf().len() <= before && !ready!(poll_grow(reader.as_mut(), cx))? {
+        return Poll::Ready(Err(UnexpectedEof {
+            requested: amt,
+            partial: before,
+        }
+        .into()));
+    }
+    Poll::Ready(Ok(()))
+}
@@ -0,0 +1,512 @@
+//! Protocol detection on buffered readers.
+//!
+//! The [`sniff`] function peeks at the start of a stream and detects the
+//! protocol spoken by the peer without consuming any data. This is typically
+//! used on a fresh connection before deciding how to parse it, or whether to
+//! switch the reader to passthrough mode.
+//!
+//! The detection logic is also available on raw bytes with [`detect`].
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use crate::AsyncBufRead;
+
+/// The HTTP/2 connection preface sent by clients.
+pub(crate) const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// The signature of a PROXY protocol v1 header.
+pub(crate) const PROXY_V1_SIGNATURE: &[u8] = b"PROXY ";
+
+/// The signature of a PROXY protocol v2 header.
+pub(crate) const PROXY_V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The protocol detected at the start of a stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Protocol {
+    /// A TLS handshake starting with a ClientHello.
+    Tls(ClientHello),
+    /// An HTTP/1.x request.
+    Http1(HttpRequest),
+    /// An HTTP/2 connection preface (prior knowledge).
+    Http2,
+    /// A PROXY protocol v1 (text) header.
+    ProxyV1,
+    /// A PROXY protocol v2 (binary) header.
+    ProxyV2,
+    /// A SOCKS4 (or SOCKS4a) request.
+    Socks4,
+    /// A SOCKS5 greeting.
+    Socks5,
+    /// An HTTP/1.1 request to upgrade to a WebSocket.
+    WebSocket(HttpRequest),
+}
+
+/// The information extracted from a TLS ClientHello.
+///
+/// A ClientHello fragmented over multiple records is parsed as far as the
+/// first record goes, so the server name might be missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHello {
+    /// The highest version offered by the client.
+    ///
+    /// This takes the `supported_versions` extension into account. If the
+    /// ClientHello is truncated, this is the version of the record.
+    pub version: TlsVersion,
+    /// The server name requested by the client (SNI), if any.
+    pub server_name: Option<String>,
+}
+
+/// A version of the TLS protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    /// SSL 3.0
+    Ssl3,
+    /// TLS 1.0
+    Tls10,
+    /// TLS 1.1
+    Tls11,
+    /// TLS 1.2
+    Tls12,
+    /// TLS 1.3
+    Tls13,
+    /// Any other version, as sent on the wire.
+    Unknown(u16),
+}
+
+impl From<u16> for TlsVersion {
+    fn from(version: u16) -> Self {
+        match version {
+            0x0300 => Self::Ssl3,
+            0x0301 => Self::Tls10,
+            0x0302 => Self::Tls11,
+            0x0303 => Self::Tls12,
+            0x0304 => Self::Tls13,
+            version => Self::Unknown(version),
+        }
+    }
+}
+
+/// The information extracted from an HTTP/1.x request line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// The request method.
+    pub method: String,
+    /// The request target.
+    pub target: String,
+    /// The minor version of HTTP/1.x.
+    pub minor_version: u8,
+}
+
+/// The error returned when the protocol could not be detected.
+///
+/// When returned by [`sniff`], it is wrapped in an [`io::Error`] and can be
+/// retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffError {
+    /// The data is the beginning of a single known protocol, but more is
+    /// needed to confirm it.
+    ///
+    /// Wrapped in an [`io::Error`] of kind [`io::ErrorKind::UnexpectedEof`].
+    Incomplete,
+    /// The data is the beginning of multiple known protocols.
+    ///
+    /// Wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidData`].
+    Ambiguous,
+    /// The data doesn't match any known protocol.
+    ///
+    /// Wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidData`].
+    Unrecognized,
+}
+
+impl fmt::Display for SniffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Incomplete => f.write_str("not enough data to detect the protocol"),
+            Self::Ambiguous => f.write_str("data matches multiple protocols"),
+            Self::Unrecognized => f.write_str("data doesn't match any known protocol"),
+        }
+    }
+}
+
+impl std::error::Error for SniffError {}
+
+impl From<SniffError> for io::Error {
+    fn from(err: SniffError) -> Self {
+        let kind = match err {
+            SniffError::Incomplete => io::ErrorKind::UnexpectedEof,
+            SniffError::Ambiguous | SniffError::Unrecognized => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Detects the protocol from the start of a stream.
+///
+/// Returns [`SniffError::Incomplete`] or [`SniffError::Ambiguous`] if more
+/// data is needed to take a decision.
+pub fn detect(buf: &[u8]) -> Result<Protocol, SniffError> {
+    let detectors: [Detector; 7] = [
+        detect_tls,
+        detect_http2,
+        detect_proxy_v2,
+        detect_proxy_v1,
+        detect_socks4,
+        detect_socks5,
+        detect_http1,
+    ];
+
+    let mut partial = 0;
+    for detector in detectors {
+        match detector(buf) {
+            Match::Yes(protocol) => return Ok(protocol),
+            Match::Partial => partial += 1,
+            Match::No => {}
+        }
+    }
+
+    match partial {
+        0 => Err(SniffError::Unrecognized),
+        1 => Err(SniffError::Incomplete),
+        _ => Err(SniffError::Ambiguous),
+    }
+}
+
+/// Detects the protocol spoken on the reader without consuming any data.
+///
+/// The buffer of the reader is filled until the protocol can be detected or
+/// `limit` bytes were peeked. HTTP/1.x requests are detected once their whole
+/// head was peeked, so `limit` must cover the headers.
+///
+/// Equivalent to:
+///
+/// ```ignore
+/// async fn sniff<R>(reader: &mut R, limit: usize) -> io::Result<Protocol>;
+/// ```
+///
+/// # Errors
+///
+/// This function will return an I/O error if the underlying reader was
+/// read, but returned an error.
+///
+/// If the protocol can't be detected, an error wrapping a [`SniffError`] is
+/// returned. The data stays in the buffer.
+///
+/// # Cancel safety
+///
+/// This method is cancel safe. If you use it as the event in a
+/// `select!` statement and some other branch completes first,
+/// then it is guaranteed that no data was read.
+pub fn sniff<R>(reader: &mut R, limit: usize) -> Sniff<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    Sniff { reader, limit }
+}
+
+/// Future for the [`sniff`] function.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Sniff<'a, R: ?Sized> {
+    reader: &'a mut R,
+    limit: usize,
+}
+
+impl<R> Future for Sniff<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    type Output = io::Result<Protocol>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.get_mut();
+        let mut reader = Pin::new(&mut *me.reader);
+        loop {
+            let buf = reader.as_ref().buf();
+            let len = std::cmp::min(buf.len(), me.limit);
+            let err = match detect(&buf[..len]) {
+                Ok(protocol) => return Poll::Ready(Ok(protocol)),
+                Err(err @ (SniffError::Incomplete | SniffError::Ambiguous)) => err,
+                Err(err) => return Poll::Ready(Err(err.into())),
+            };
+
+            if len >= me.limit {
+                return Poll::Ready(Err(err.into()));
+            }
+            ready!(reader.as_mut().poll_fill_buf(cx, len + 1))?;
+            if reader.as_ref().buf().len() <= len {
+                return Poll::Ready(Err(err.into()));
+            }
+        }
+    }
+}
+
+/// A function detecting a single protocol.
+type Detector = fn(&[u8]) -> Match<Protocol>;
+
+/// The result of a single protocol detector.
+enum Match<T> {
+    /// The data matches the protocol.
+    Yes(T),
+    /// The data is a prefix of the protocol.
+    Partial,
+    /// The data doesn't match the protocol.
+    No,
+}
+
+fn detect_prefix(buf: &[u8], signature: &[u8], protocol: Protocol) -> Match<Protocol> {
+    if buf.len() < signature.len() {
+        if signature.starts_with(buf) {
+            Match::Partial
+        } else {
+            Match::No
+        }
+    } else if buf.starts_with(signature) {
+        Match::Yes(protocol)
+    } else {
+        Match::No
+    }
+}
+
+fn detect_http2(buf: &[u8]) -> Match<Protocol> {
+    detect_prefix(buf, HTTP2_PREFACE, Protocol::Http2)
+}
+
+fn detect_proxy_v1(buf: &[u8]) -> Match<Protocol> {
+    detect_prefix(buf, PROXY_V1_SIGNATURE, Protocol::ProxyV1)
+}
+
+fn detect_proxy_v2(buf: &[u8]) -> Match<Protocol> {
+    match detect_prefix(buf, PROXY_V2_SIGNATURE, Protocol::ProxyV2) {
+        // The version is in the high nibble of the next byte
+        Match::Yes(protocol) => match buf.get(PROXY_V2_SIGNATURE.len()) {
+            Some(byte) if byte >> 4 == 2 => Match::Yes(protocol),
+            Some(_) => Match::No,
+            None => Match::Partial,
+        },
+        other => other,
+    }
+}
+
+fn detect_socks4(buf: &[u8]) -> Match<Protocol> {
+    // VER CMD DSTPORT(2) DSTIP(4) USERID NUL
+    match buf {
+        [] | [0x04] => Match::Partial,
+        [0x04, 0x01 | 0x02, ..] if buf.len() < 9 => Match::Partial,
+        [0x04, 0x01 | 0x02, ..] if buf[8..].contains(&0) => Match::Yes(Protocol::Socks4),
+        [0x04, 0x01 | 0x02, ..] => Match::Partial,
+        _ => Match::No,
+    }
+}
+
+fn detect_socks5(buf: &[u8]) -> Match<Protocol> {
+    // VER NMETHODS METHODS
+    let (nmethods, methods) = match buf {
+        [] | [0x05] => return Match::Partial,
+        [0x05, 0, ..] => return Match::No,
+        [0x05, nmethods, methods @ ..] => (*nmethods as usize, methods),
+        _ => return Match::No,
+    };
+    let valid = |method: &u8| matches!(method, 0x00..=0x09 | 0x80..=0xFE);
+    if !methods.iter().take(nmethods).all(valid) {
+        Match::No
+    } else if methods.len() < nmethods {
+        Match::Partial
+    } else {
+        Match::Yes(Protocol::Socks5)
+    }
+}
+
+fn detect_tls(buf: &[u8]) -> Match<Protocol> {
+    // Record header: type(1) version(2) length(2), then the handshake type
+    match buf {
+        [] | [0x16] | [0x16, 0x03] => return Match::Partial,
+        [0x16, 0x03, 0x00..=0x04, ..] => {}
+        _ => return Match::No,
+    }
+    if buf.len() < 6 {
+        return Match::Partial;
+    }
+    if buf[5] != 0x01 {
+        return Match::No;
+    }
+    let record_len = u16::from_be_bytes([buf[3], buf[4]]) as usize;
+    if buf.len() < 5 + record_len {
+        return Match::Partial;
+    }
+    let mut hello = ClientHello {
+        version: TlsVersion::from(u16::from_be_bytes([buf[1], buf[2]])),
+        server_name: None,
+    };
+    // A ClientHello fragmented over multiple records is parsed as far as
+    // possible, the record header is enough to detect TLS
+    parse_client_hello(&buf[5..5 + record_len], &mut hello);
+    Match::Yes(Protocol::Tls(hello))
+}
+
+fn parse_client_hello(record: &[u8], client_hello: &mut ClientHello) -> Option<()> {
+    let mut handshake = Cursor::new(record);
+    handshake.u8()?;
+    let len = handshake.u24()? as usize;
+    let len = std::cmp::min(len, handshake.remaining());
+    let mut hello = Cursor::new(handshake.bytes(len)?);
+
+    // Versions are compared on the wire, unknown ones included
+    let mut version = hello.u16()?;
+    client_hello.version = TlsVersion::from(version);
+    hello.bytes(32)?;
+    let len = hello.u8()? as usize;
+    hello.bytes(len)?;
+    let len = hello.u16()? as usize;
+    hello.bytes(len)?;
+    let len = hello.u8()? as usize;
+    hello.bytes(len)?;
+
+    // Extensions are optional
+    let len = hello.u16()?;
+    let mut extensions = Cursor::new(hello.bytes(len as usize).unwrap_or_default());
+    while let (Some(kind), Some(len)) = (extensions.u16(), extensions.u16()) {
+        let Some(data) = extensions.bytes(len as usize) else {
+            break;
+        };
+        let mut data = Cursor::new(data);
+        match kind {
+            // server_name
+            0x0000 => {
+                let len = data.u16()? as usize;
+                let mut list = Cursor::new(data.bytes(len)?);
+                while let Some(name_type) = list.u8() {
+                    let len = list.u16()? as usize;
+                    let name = list.bytes(len)?;
+                    if name_type == 0 {
+                        client_hello.server_name = String::from_utf8(name.to_vec()).ok();
+                        break;
+                    }
+                }
+            }
+            // supported_versions
+            0x002b => {
+                let len = data.u8()? as usize;
+                let mut list = Cursor::new(data.bytes(len)?);
+                while let Some(supported) = list.u16() {
+                    // Skip GREASE values
+                    if supported & 0x0f0f == 0x0a0a {
+                        continue;
+                    }
+                    version = std::cmp::max(version, supported);
+                    client_hello.version = TlsVersion::from(version);
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(())
+}
+
+fn detect_http1(buf: &[u8]) -> Match<Protocol> {
+    // Request line: METHOD SP TARGET SP HTTP/1.x CRLF
+    let Some(method_len) = buf.iter().position(|b| !is_tchar(*b)) else {
+        return Match::Partial;
+    };
+    if method_len == 0 || buf[method_len] != b' ' {
+        return Match::No;
+    }
+    let rest = &buf[method_len + 1..];
+    let Some(target_len) = rest.iter().position(|b| !(0x21..=0x7e).contains(b)) else {
+        return Match::Partial;
+    };
+    if target_len == 0 || rest[target_len] != b' ' {
+        return Match::No;
+    }
+    let rest = &rest[target_len + 1..];
+    const VERSION: &[u8] = b"HTTP/1.";
+    if rest.len() < VERSION.len() + 3 {
+        let len = std::cmp::min(rest.len(), VERSION.len());
+        return match VERSION.starts_with(&rest[..len])
+            && rest[len..].first().is_none_or(u8::is_ascii_digit)
+            && rest.get(len + 1).is_none_or(|b| *b == b'\r')
+        {
+            true => Match::Partial,
+            false => Match::No,
+        };
+    }
+    let minor_version = rest[VERSION.len()];
+    if !rest.starts_with(VERSION)
+        || !minor_version.is_ascii_digit()
+        || &rest[VERSION.len() + 1..VERSION.len() + 3] != b"\r\n"
+    {
+        return Match::No;
+    }
+
+    // The whole head is needed to tell WebSocket upgrades apart, so the
+    // result doesn't depend on how the data arrived
+    let head = &rest[VERSION.len() + 1..];
+    let Some(end) = crate::scan::find(head, b"\r\n\r\n") else {
+        return Match::Partial;
+    };
+    let request = HttpRequest {
+        method: String::from_utf8_lossy(&buf[..method_len]).into_owned(),
+        target: String::from_utf8_lossy(&buf[method_len + 1..method_len + 1 + target_len])
+            .into_owned(),
+        minor_version: minor_version - b'0',
+    };
+    let websocket = head[..end].split(|b| *b == b'\n').any(|line| {
+        let Some(colon) = line.iter().position(|b| *b == b':') else {
+            return false;
+        };
+        let (name, value) = (&line[..colon], &line[colon + 1..]);
+        name.eq_ignore_ascii_case(b"upgrade")
+            && value
+                .split(|b| *b == b',')
+                .any(|token| token.trim_ascii().eq_ignore_ascii_case(b"websocket"))
+    });
+    if websocket {
+        Match::Yes(Protocol::WebSocket(request))
+    } else {
+        Match::Yes(Protocol::Http1(request))
+    }
+}
+
+fn is_tchar(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+/// A minimal cursor to parse binary data.
+struct Cursor<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn remaining(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.buf.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self) -> Option<u32> {
+        self.bytes(3)
+            .map(|b| u32::from_be_bytes([0, b[0], b[1], b[2]]))
+    }
+}
@@ -0,0 +1,162 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::Bytes;
+use futures_core::Stream;
+
+use crate::AsyncBufRead;
+use crate::scan::poll_scan;
+
+/// The line ending used by [`lines`](crate::AsyncBufReadExt::lines).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Lines end with `\n` or `\r\n`.
+    #[default]
+    Any,
+    /// Lines end with `\n`, a preceding `\r` is part of the line.
+    Lf,
+    /// Lines end with `\r\n`, a lone `\n` is part of the line.
+    Crlf,
+}
+
+pub(crate) fn lines<R>(reader: &mut R, limit: usize) -> Lines<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    Lines {
+        reader,
+        line_ending: LineEnding::default(),
+        limit,
+        scanned: 0,
+    }
+}
+
+pub(crate) fn split<R>(reader: &mut R, delimiter: u8, limit: usize) -> Split<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    Split {
+        reader,
+        delimiter,
+        limit,
+        scanned: 0,
+    }
+}
+
+/// Stream for the [`lines`](crate::AsyncBufReadExt::lines) method.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Lines<'a, R: ?Sized> {
+    reader: &'a mut R,
+    line_ending: LineEnding,
+    limit: usize,
+    scanned: usize,
+}
+
+impl<R: ?Sized> Lines<'_, R> {
+    /// Sets the line ending, [`LineEnding::Any`] by default.
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+}
+
+impl<R> Stream for Lines<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = self.get_mut();
+        let delimiter: &[u8] = match me.line_ending {
+            LineEnding::Any | LineEnding::Lf => b"\n",
+            LineEnding::Crlf => b"\r\n",
+        };
+        let reader = Pin::new(&mut *me.reader);
+        let Some((mut line, delimited)) = ready!(poll_next_segment(
+            reader,
+            cx,
+            delimiter,
+            &mut me.scanned,
+            me.limit
+        )?) else {
+            return Poll::Ready(None);
+        };
+        if delimited {
+            let mut len = line.len() - delimiter.len();
+            if me.line_ending == LineEnding::Any && line[..len].ends_with(b"\r") {
+                len -= 1;
+            }
+            line.truncate(len);
+        }
+        Poll::Ready(Some(Ok(line)))
+    }
+}
+
+/// Stream for the [`split`](crate::AsyncBufReadExt::split) method.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Split<'a, R: ?Sized> {
+    reader: &'a mut R,
+    delimiter: u8,
+    limit: usize,
+    scanned: usize,
+}
+
+impl<R> Stream for Split<'_, R>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = self.get_mut();
+        let delimiter = [me.delimiter];
+        let reader = Pin::new(&mut *me.reader);
+        let Some((mut segment, delimited)) = ready!(poll_next_segment(
+            reader,
+            cx,
+            &delimiter,
+            &mut me.scanned,
+            me.limit
+        )?) else {
+            return Poll::Ready(None);
+        };
+        if delimited {
+            segment.truncate(segment.len() - 1);
+        }
+        Poll::Ready(Some(Ok(segment)))
+    }
+}
+
+/// Splits the next segment from the reader, including the delimiter.
+///
+/// Returns whether the segment ends with the delimiter, the last segment
+/// before EOF might not. [`poll_scan`] only returns `None` at EOF, so the
+/// partial data of a buffer that stops growing before that, for example in
+/// passthrough mode, is never split as a final segment.
+fn poll_next_segment<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    delimiter: &[u8],
+    scanned: &mut usize,
+    limit: usize,
+) -> Poll<io::Result<Option<(Bytes, bool)>>>
+where
+    R: AsyncBufRead + ?Sized,
+{
+    let end = ready!(poll_scan(reader.as_mut(), cx, delimiter, scanned, limit))?;
+    *scanned = 0;
+    match end {
+        Some(end) => Poll::Ready(Ok(Some((reader.split_to(end), true)))),
+        None => {
+            let len = reader.as_ref().buf().len();
+            if len == 0 {
+                return Poll::Ready(Ok(None));
+            }
+            Poll::Ready(Ok(Some((reader.split_to(len), false))))
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::Bytes;
+use pin_project_lite::pin_project;
+
+use crate::io::{self as rt, AsyncRead, ReadBuf};
+use crate::{AsyncBufPassthrough, AsyncBufRead, UnexpectedEof};
+
+pin_project! {
+    /// Reader for the [`take`](crate::AsyncBufReadExt::take) method.
+    ///
+    /// It only exposes the next `limit` bytes of the underlying reader and
+    /// reports EOF once they are all buffered, so peeking past the limit fails
+    /// like at the end of a stream. The data after the limit is left in the
+    /// underlying reader.
+    #[derive(Debug)]
+    pub struct Take<R> {
+        #[pin]
+        reader: R,
+        limit: u64,
+    }
+}
+
+pub(crate) fn take<R: AsyncBufRead>(reader: R, limit: u64) -> Take<R> {
+    Take { reader, limit }
+}
+
+impl<R> Take<R> {
+    /// Returns the number of bytes that can still be read.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Sets the number of bytes that can still be read.
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Care should be taken to avoid modifying the internal I/O state of the
+    /// underlying reader as doing so may corrupt the limit.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// Care should be taken to avoid modifying the internal I/O state of the
+    /// underlying reader as doing so may corrupt the limit.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().reader
+    }
+
+    /// Consumes this `Take`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead> Take<R> {
+    fn poll_read_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let me = self.project();
+        if *me.limit == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        let limit = usize::try_from(*me.limit).unwrap_or(usize::MAX);
+        let before = buf.filled().len();
+        ready!(rt::poll_read_buf_limited(me.reader, cx, buf, limit))?;
+        *me.limit -= (buf.filled().len() - before) as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<R> for Take<R> where R: AsyncRead);
+
+impl<R: AsyncBufRead> AsyncBufRead for Take<R> {
+    fn eof(self: Pin<&Self>) -> bool {
+        let me = self.project_ref();
+        me.reader.buf().len() as u64 >= *me.limit || me.reader.eof()
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        let me = self.project_ref();
+        let buf = me.reader.buf();
+        let len = std::cmp::min(buf.len() as u64, *me.limit) as usize;
+        &buf[..len]
+    }
+
+    fn poll_fill_buf<'a>(
+        self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        let me = self.project();
+        let amt = std::cmp::min(amt as u64, *me.limit) as usize;
+        let buf = ready!(me.reader.poll_fill_buf(cx, amt))?;
+        Poll::Ready(Ok(&buf[..std::cmp::min(buf.len(), amt)]))
+    }
+
+    fn poll_fill_buf_exact<'a>(
+        self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        // The underlying reader knows best when it can't provide more data
+        let me = self.project();
+        let limited = std::cmp::min(amt as u64, *me.limit) as usize;
+        let buf = ready!(me.reader.poll_fill_buf_exact(cx, limited))?;
+        if limited < amt {
+            return Poll::Ready(Err(UnexpectedEof {
+                requested: amt,
+                partial: limited,
+            }
+            .into()));
+        }
+        Poll::Ready(Ok(&buf[..amt]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let me = self.project();
+        assert!(amt as u64 <= *me.limit, "cannot consume past the limit");
+        *me.limit -= amt as u64;
+        me.reader.consume(amt);
+    }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        let me = self.project();
+        assert!(amt as u64 <= *me.limit, "cannot consume past the limit");
+        *me.limit -= amt as u64;
+        me.reader.split_to(amt)
+    }
+}
+
+impl<R: AsyncBufPassthrough> AsyncBufPassthrough for Take<R> {
+    fn passthrough(&mut self, enabled: bool) {
+        self.reader.passthrough(enabled);
+    }
+}
@@ -0,0 +1,317 @@
+//! Recording of the data read from a buffered reader.
+//!
+//! The [`Tee`] reader mirrors every byte that is consumed or read from the
+//! underlying reader to a [`TeeSink`], like a `BytesMut` or any `AsyncWrite`
+//! wrapped in a [`WriteSink`]. The data that is only peeked is not recorded,
+//! so a parser can look ahead without duplicating bytes in the record.
+
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker, ready};
+
+use bytes::{Buf, Bytes, BytesMut};
+use pin_project_lite::pin_project;
+
+use crate::io::{self as rt, AsyncRead, AsyncWrite, ReadBuf};
+use crate::{AsyncBufPassthrough, AsyncBufRead};
+
+/// A destination for the data recorded by a [`Tee`].
+pub trait TeeSink {
+    /// Attempts to write data to the sink, returning how much was written.
+    fn poll_record(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>>;
+
+    /// Attempts to flush the data written to the sink.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+impl TeeSink for BytesMut {
+    fn poll_record(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+pin_project! {
+    /// A [`TeeSink`] writing to an `AsyncWrite`.
+    #[derive(Debug)]
+    pub struct WriteSink<W> {
+        #[pin]
+        writer: W,
+    }
+}
+
+impl<W> WriteSink<W> {
+    /// Creates a new `WriteSink` writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Consumes this `WriteSink`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: AsyncWrite> TeeSink for WriteSink<W> {
+    fn poll_record(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().writer.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().writer.poll_flush(cx)
+    }
+}
+
+/// What a [`Tee`] does when its sink is not ready to accept more data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Keep the data until the sink accepts it, reading is paused meanwhile.
+    #[default]
+    Block,
+    /// Drop the data that the sink doesn't accept by the next read.
+    ///
+    /// The data of each consume, split or read is dropped as a whole, a part
+    /// that the sink started to accept is still written in full.
+    Drop,
+}
+
+pin_project! {
+    /// The `Tee` struct records the data read from a buffered reader.
+    ///
+    /// The data passed to [`consume`] or [`split_to`], and the data read
+    /// through the runtime `AsyncRead`, including in passthrough mode, is
+    /// written to the sink in order. It is written right away when the sink is
+    /// ready, otherwise the [`Backpressure`] decides whether the next reads
+    /// wait for the sink or the data is dropped.
+    ///
+    /// With [`Backpressure::Block`], call [`poll_flush`] once done reading to
+    /// make sure all the data reached the sink.
+    ///
+    /// [`consume`]: AsyncBufRead::consume
+    /// [`split_to`]: AsyncBufRead::split_to
+    /// [`poll_flush`]: Tee::poll_flush
+    #[derive(Debug)]
+    pub struct Tee<R, W> {
+        #[pin]
+        reader: R,
+        #[pin]
+        sink: W,
+        backpressure: Backpressure,
+        // Data that was read but not written to the sink yet, one chunk per
+        // consume, split or read
+        pending: VecDeque<Bytes>,
+        // Whether the sink accepted part of the first pending chunk
+        started: bool,
+        dropped: u64,
+    }
+}
+
+impl<R, W: TeeSink> Tee<R, W> {
+    /// Creates a new `Tee` recording the data read from `reader` to `sink`.
+    pub fn new(reader: R, sink: W) -> Self {
+        Self {
+            reader,
+            sink,
+            backpressure: Backpressure::default(),
+            pending: VecDeque::new(),
+            started: false,
+            dropped: 0,
+        }
+    }
+
+    /// Sets what happens when the sink is not ready, [`Backpressure::Block`] by default.
+    pub fn with_backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Returns the number of bytes that were dropped because the sink was
+    /// not ready.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// The data read directly from the underlying reader is not recorded.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// The data read directly from the underlying reader is not recorded.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().reader
+    }
+
+    /// Gets a reference to the sink.
+    pub fn sink(&self) -> &W {
+        &self.sink
+    }
+
+    /// Gets a mutable reference to the sink.
+    pub fn sink_mut(&mut self) -> &mut W {
+        &mut self.sink
+    }
+
+    /// Consumes this `Tee`, returning the underlying reader and the sink.
+    ///
+    /// The data that was not written to the sink yet is lost, see
+    /// [`poll_flush`](Tee::poll_flush).
+    pub fn into_parts(self) -> (R, W) {
+        (self.reader, self.sink)
+    }
+
+    /// Attempts to write all the recorded data to the sink and flush it.
+    pub fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut me = self.project();
+        ready!(poll_write_pending(
+            me.sink.as_mut(),
+            cx,
+            me.pending,
+            me.started
+        ))?;
+        me.sink.poll_flush(cx)
+    }
+
+    /// Records `data`, writing it right away if the sink is ready.
+    fn record(self: Pin<&mut Self>, data: Bytes) {
+        let me = self.project();
+        if data.is_empty() {
+            return;
+        }
+        me.pending.push_back(data);
+        // Nothing is dropped here, since the sink can't wake up the reader
+        let mut cx = Context::from_waker(Waker::noop());
+        // Errors are returned by the next read
+        let _ = poll_write_pending(me.sink, &mut cx, me.pending, me.started);
+    }
+
+    /// Waits for the sink to accept the recorded data before reading more.
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let me = self.project();
+        if me.pending.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        match poll_write_pending(me.sink, cx, me.pending, me.started) {
+            Poll::Pending if *me.backpressure == Backpressure::Drop => {
+                // The rest of a chunk that the sink started to accept is kept
+                let keep = usize::from(*me.started);
+                let dropped = me.pending.iter().skip(keep).map(Bytes::len).sum::<usize>();
+                *me.dropped += dropped as u64;
+                me.pending.truncate(keep);
+                Poll::Ready(Ok(()))
+            }
+            poll => poll,
+        }
+    }
+}
+
+impl<R: AsyncRead, W: TeeSink> Tee<R, W> {
+    fn poll_read_buf(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_ready(cx))?;
+        let before = buf.filled().len();
+        ready!(rt::poll_read_buf(self.as_mut().project().reader, cx, buf))?;
+        let data = Bytes::copy_from_slice(&buf.filled()[before..]);
+        self.record(data);
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<R, W> for Tee<R, W> where R: AsyncRead, W: TeeSink);
+
+impl<R: AsyncBufRead, W: TeeSink> AsyncBufRead for Tee<R, W> {
+    fn eof(self: Pin<&Self>) -> bool {
+        self.project_ref().reader.eof()
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        self.project_ref().reader.buf()
+    }
+
+    fn poll_fill_buf<'a>(
+        mut self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        ready!(self.as_mut().poll_ready(cx))?;
+        self.project().reader.poll_fill_buf(cx, amt)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        let reader = self.as_mut().project().reader;
+        let data = Bytes::copy_from_slice(&reader.as_ref().buf()[..amt]);
+        reader.consume(amt);
+        self.record(data);
+    }
+
+    fn split_to(mut self: Pin<&mut Self>, amt: usize) -> Bytes {
+        let data = self.as_mut().project().reader.split_to(amt);
+        self.record(data.clone());
+        data
+    }
+}
+
+impl<R: AsyncBufPassthrough, W> AsyncBufPassthrough for Tee<R, W> {
+    fn passthrough(&mut self, enabled: bool) {
+        self.reader.passthrough(enabled);
+    }
+}
+
+/// Writes the pending chunks to the sink until there are none left.
+fn poll_write_pending<W: TeeSink + ?Sized>(
+    mut sink: Pin<&mut W>,
+    cx: &mut Context<'_>,
+    pending: &mut VecDeque<Bytes>,
+    started: &mut bool,
+) -> Poll<io::Result<()>> {
+    while let Some(chunk) = pending.front_mut() {
+        let n = ready!(sink.as_mut().poll_record(cx, chunk))?;
+        if n == 0 {
+            return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+        }
+        chunk.advance(n);
+        *started = !chunk.is_empty();
+        if chunk.is_empty() {
+            pending.pop_front();
+        }
+    }
+    Poll::Ready(Ok(()))
+}
@@ -0,0 +1,249 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+
+use bytes::Bytes;
+use pin_project_lite::pin_project;
+use tokio::time::{Instant, Sleep, sleep_until};
+
+use crate::io::{self as rt, ReadBuf};
+use crate::{AsyncBufPassthrough, AsyncBufRead};
+
+pin_project! {
+    /// The `Throttled` struct limits the rate at which data is read from a
+    /// buffered reader, using a token bucket and the `tokio` timer.
+    ///
+    /// The bucket holds up to `burst` bytes and is refilled with `rate` bytes
+    /// per second. Reads wait until the bucket is not empty, then request at
+    /// most the available amount from the underlying reader. A reader that
+    /// reads in chunks can pull more than that, the excess is then paid before
+    /// the next read, so the average rate is respected.
+    ///
+    /// Only the data pulled from the underlying reader is throttled, peeking
+    /// or reading data that is already buffered returns right away.
+    #[derive(Debug)]
+    pub struct Throttled<R> {
+        #[pin]
+        reader: R,
+        bucket: Bucket,
+        // Created on the first wait, so the reader can be built outside of a runtime
+        sleep: Option<Pin<Box<Sleep>>>,
+    }
+}
+
+/// A token bucket counting the bytes that can be read.
+#[derive(Debug)]
+struct Bucket {
+    rate: u64,
+    burst: u64,
+    // Negative when the underlying reader pulled more than was available
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    /// Adds the tokens earned since the last refill.
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let earned = (now - self.last_refill).as_secs_f64() * self.rate as f64;
+        self.tokens = (self.tokens + earned).min(self.burst as f64);
+        self.last_refill = now;
+    }
+}
+
+impl<R> Throttled<R> {
+    /// Creates a new `Throttled` reading at most `rate` bytes per second from
+    /// `reader`.
+    ///
+    /// The burst is `rate` by default, and the bucket starts full.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `rate` is 0.
+    pub fn new(reader: R, rate: u64) -> Self {
+        assert!(rate > 0, "rate must be greater than 0");
+        Self {
+            reader,
+            bucket: Bucket {
+                rate,
+                burst: rate,
+                tokens: rate as f64,
+                last_refill: Instant::now(),
+            },
+            sleep: None,
+        }
+    }
+
+    /// Sets the maximum amount of bytes that can be read at once after the
+    /// reader was idle, and fills the bucket.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `burst` is 0.
+    pub fn with_burst(mut self, burst: u64) -> Self {
+        assert!(burst > 0, "burst must be greater than 0");
+        self.bucket.burst = burst;
+        self.bucket.tokens = burst as f64;
+        self
+    }
+
+    /// Returns the number of bytes that can be read per second.
+    pub fn rate(&self) -> u64 {
+        self.bucket.rate
+    }
+
+    /// Sets the number of bytes that can be read per second.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `rate` is 0.
+    pub fn set_rate(&mut self, rate: u64) {
+        assert!(rate > 0, "rate must be greater than 0");
+        self.bucket.refill();
+        self.bucket.rate = rate;
+    }
+
+    /// Returns the maximum amount of bytes that can be read at once.
+    pub fn burst(&self) -> u64 {
+        self.bucket.burst
+    }
+
+    /// Sets the maximum amount of bytes that can be read at once.
+    ///
+    /// The bytes available in the bucket are capped to the new burst.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `burst` is 0.
+    pub fn set_burst(&mut self, burst: u64) {
+        assert!(burst > 0, "burst must be greater than 0");
+        self.bucket.refill();
+        self.bucket.burst = burst;
+        self.bucket.tokens = self.bucket.tokens.min(burst as f64);
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// The data read directly from the underlying reader is not throttled.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// The data read directly from the underlying reader is not throttled.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().reader
+    }
+
+    /// Consumes this `Throttled`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Waits until at least one byte can be read, returning how many can be.
+    fn poll_tokens(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+        let me = self.project();
+        loop {
+            me.bucket.refill();
+            if me.bucket.tokens >= 1.0 {
+                return Poll::Ready(me.bucket.tokens as usize);
+            }
+
+            let wait = (1.0 - me.bucket.tokens) / me.bucket.rate as f64;
+            let deadline = me.bucket.last_refill + Duration::from_secs_f64(wait);
+            let sleep = me
+                .sleep
+                .get_or_insert_with(|| Box::pin(sleep_until(deadline)));
+            sleep.as_mut().reset(deadline);
+            ready!(sleep.as_mut().poll(cx));
+        }
+    }
+}
+
+impl<R: AsyncBufRead> Throttled<R> {
+    fn poll_read_buf(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        // The buffered data was paid for when it was pulled
+        let buffered = self.as_ref().project_ref().reader.buf();
+        if !buffered.is_empty() {
+            let amt = std::cmp::min(buf.remaining(), buffered.len());
+            buf.put_slice(&buffered[..amt]);
+            self.project().reader.consume(amt);
+            return Poll::Ready(Ok(()));
+        }
+
+        let limit = ready!(self.as_mut().poll_tokens(cx));
+        let mut me = self.project();
+        let before = buf.filled().len();
+        ready!(rt::poll_read_buf_limited(
+            me.reader.as_mut(),
+            cx,
+            buf,
+            limit
+        ))?;
+        // The reader can buffer more than it returned
+        let pulled = buf.filled().len() - before + me.reader.as_ref().buf().len();
+        me.bucket.tokens -= pulled as f64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+rt::impl_async_read!(impl<R> for Throttled<R> where R: AsyncBufRead);
+
+impl<R: AsyncBufRead> AsyncBufRead for Throttled<R> {
+    fn eof(self: Pin<&Self>) -> bool {
+        self.project_ref().reader.eof()
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        self.project_ref().reader.buf()
+    }
+
+    fn poll_fill_buf<'a>(
+        mut self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        let before = self.as_ref().project_ref().reader.buf().len();
+        if before >= amt || self.as_ref().project_ref().reader.eof() {
+            return self.project().reader.poll_fill_buf(cx, amt);
+        }
+
+        let tokens = ready!(self.as_mut().poll_tokens(cx));
+        let mut me = self.as_mut().project();
+        let limited = std::cmp::min(amt, before.saturating_add(tokens));
+        let len = ready!(me.reader.as_mut().poll_fill_buf(cx, limited))?.len();
+        let after = me.reader.as_ref().buf().len();
+        me.bucket.tokens -= after.saturating_sub(before) as f64;
+        Poll::Ready(Ok(&self.into_ref().buf()[..len]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.project().reader.consume(amt);
+    }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        self.project().reader.split_to(amt)
+    }
+}
+
+impl<R: AsyncBufPassthrough> AsyncBufPassthrough for Throttled<R> {
+    fn passthrough(&mut self, enabled: bool) {
+        self.reader.passthrough(enabled);
+    }
+}
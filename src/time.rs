@@ -0,0 +1,281 @@
+//! Timeouts for buffered readers, using the `tokio` timer.
+//!
+//! The `_timeout` methods of [`AsyncBufReadExt`], like [`peek_timeout`], fail
+//! a single request that takes too long. The [`Deadline`] reader bounds the
+//! total time spent reading and the time spent waiting for new data, which
+//! protects against peers that send data slowly to hold a connection open.
+//!
+//! In both cases, a timeout doesn't discard the data that was buffered.
+//!
+//! [`AsyncBufReadExt`]: crate::AsyncBufReadExt
+//! [`peek_timeout`]: crate::AsyncBufReadExt::peek_timeout
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+
+use bytes::Bytes;
+use pin_project_lite::pin_project;
+use tokio::time::{Instant, Sleep, sleep, sleep_until};
+
+use crate::io::{self as rt, AsyncRead, ReadBuf};
+use crate::{AsyncBufPassthrough, AsyncBufRead};
+
+/// The error returned when a request or a [`Deadline`] timed out.
+///
+/// It is wrapped in an [`io::Error`] of kind [`io::ErrorKind::TimedOut`]
+/// and can be retrieved with [`io::Error::get_ref`] and `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut {
+    /// The timeout that elapsed.
+    pub timeout: Duration,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "timed out after {:?}", self.timeout)
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+impl From<TimedOut> for io::Error {
+    fn from(err: TimedOut) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, err)
+    }
+}
+
+pub(crate) fn timeout<F>(future: F, duration: Duration) -> Timeout<F> {
+    Timeout {
+        future,
+        sleep: sleep(duration),
+        duration,
+    }
+}
+
+pin_project! {
+    /// Future for the `_timeout` methods of
+    /// [`AsyncBufReadExt`](crate::AsyncBufReadExt).
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Timeout<F> {
+        #[pin]
+        future: F,
+        #[pin]
+        sleep: Sleep,
+        duration: Duration,
+    }
+}
+
+impl<F, T> Future for Timeout<F>
+where
+    F: Future<Output = io::Result<T>>,
+{
+    type Output = io::Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.project();
+        if let Poll::Ready(res) = me.future.poll(cx) {
+            return Poll::Ready(res);
+        }
+        ready!(me.sleep.poll(cx));
+        Poll::Ready(Err(TimedOut {
+            timeout: *me.duration,
+        }
+        .into()))
+    }
+}
+
+pin_project! {
+    /// The `Deadline` struct limits the time spent reading from a buffered
+    /// reader.
+    ///
+    /// Two limits can be set:
+    ///
+    /// - the total timeout, counted from the creation of the `Deadline`,
+    /// - the idle timeout, counted from the last time new data was read.
+    ///
+    /// When a limit is exceeded while waiting for the underlying reader,
+    /// `poll_fill_buf` and reads fail with an error of kind
+    /// [`TimedOut`](io::ErrorKind::TimedOut) wrapping a [`TimedOut`]. The
+    /// buffered data stays intact, so it can still be peeked and consumed,
+    /// and [`reset`] restarts the timers.
+    ///
+    /// [`reset`]: Deadline::reset
+    #[derive(Debug)]
+    pub struct Deadline<R> {
+        #[pin]
+        reader: R,
+        total_timeout: Option<Duration>,
+        idle_timeout: Option<Duration>,
+        started: Instant,
+        last_progress: Instant,
+        // Created on the first wait, so the reader can be built outside of a runtime
+        sleep: Option<Pin<Box<Sleep>>>,
+    }
+}
+
+impl<R> Deadline<R> {
+    /// Creates a new `Deadline` without limits reading from `reader`.
+    pub fn new(reader: R) -> Self {
+        let now = Instant::now();
+        Self {
+            reader,
+            total_timeout: None,
+            idle_timeout: None,
+            started: now,
+            last_progress: now,
+            sleep: None,
+        }
+    }
+
+    /// Sets the maximum time spent reading.
+    pub fn with_total_timeout(mut self, timeout: Duration) -> Self {
+        self.total_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum time spent waiting for new data.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the maximum time spent reading.
+    pub fn total_timeout(&self) -> Option<Duration> {
+        self.total_timeout
+    }
+
+    /// Returns the maximum time spent waiting for new data.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Restarts the total and idle timers from now.
+    pub fn reset(&mut self) {
+        let now = Instant::now();
+        self.started = now;
+        self.last_progress = now;
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// The data read directly from the underlying reader doesn't reset the
+    /// idle timer.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// The data read directly from the underlying reader doesn't reset the
+    /// idle timer.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().reader
+    }
+
+    /// Consumes this `Deadline`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns the next instant at which a limit is exceeded, and that limit.
+    fn next_deadline(&self) -> Option<(Instant, Duration)> {
+        let total = self.total_timeout.map(|t| (self.started + t, t));
+        let idle = self.idle_timeout.map(|t| (self.last_progress + t, t));
+        match (total, idle) {
+            (Some(total), Some(idle)) => Some(std::cmp::min_by_key(total, idle, |d| d.0)),
+            (total, idle) => total.or(idle),
+        }
+    }
+
+    /// Registers the timer of the next deadline, failing if it has passed.
+    fn poll_expired(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Error> {
+        let Some((deadline, timeout)) = self.next_deadline() else {
+            return Poll::Pending;
+        };
+        let me = self.project();
+        let sleep = me
+            .sleep
+            .get_or_insert_with(|| Box::pin(sleep_until(deadline)));
+        if sleep.deadline() != deadline {
+            sleep.as_mut().reset(deadline);
+        }
+        ready!(sleep.as_mut().poll(cx));
+        Poll::Ready(TimedOut { timeout }.into())
+    }
+}
+
+impl<R: AsyncRead> Deadline<R> {
+    fn poll_read_buf(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let me = self.as_mut().project();
+        let before = buf.filled().len();
+        match rt::poll_read_buf(me.reader, cx, buf) {
+            Poll::Ready(res) => {
+                if buf.filled().len() > before {
+                    *me.last_progress = Instant::now();
+                }
+                Poll::Ready(res)
+            }
+            Poll::Pending => self.poll_expired(cx).map(Err),
+        }
+    }
+}
+
+rt::impl_async_read!(impl<R> for Deadline<R> where R: AsyncRead);
+
+impl<R: AsyncBufRead> AsyncBufRead for Deadline<R> {
+    fn eof(self: Pin<&Self>) -> bool {
+        self.project_ref().reader.eof()
+    }
+
+    fn buf(self: Pin<&Self>) -> &[u8] {
+        self.project_ref().reader.buf()
+    }
+
+    fn poll_fill_buf<'a>(
+        mut self: Pin<&'a mut Self>,
+        cx: &mut Context<'_>,
+        amt: usize,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        let mut me = self.as_mut().project();
+        let before = me.reader.as_ref().buf().len();
+        match me.reader.as_mut().poll_fill_buf(cx, amt) {
+            Poll::Ready(Ok(buf)) => {
+                let len = buf.len();
+                if me.reader.as_ref().buf().len() > before {
+                    *me.last_progress = Instant::now();
+                }
+                Poll::Ready(Ok(&self.into_ref().buf()[..len]))
+            }
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => self.poll_expired(cx).map(Err),
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.project().reader.consume(amt);
+    }
+
+    fn split_to(self: Pin<&mut Self>, amt: usize) -> Bytes {
+        self.project().reader.split_to(amt)
+    }
+}
+
+impl<R: AsyncBufPassthrough> AsyncBufPassthrough for Deadline<R> {
+    fn passthrough(&mut self, enabled: bool) {
+        self.reader.passthrough(enabled);
+    }
+}
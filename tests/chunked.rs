@@ -0,0 +1,84 @@
+use std::io;
+
+use async_buf_read::{
+    AsyncBufReadExt, AsyncBufReader, ChunkedDecoder, ChunkedError, LimitExceeded,
+};
+use bytes::Bytes;
+
+use self::common::{AsyncReadExt, Trickle};
+
+mod common;
+
+const BODY: &[u8] = b"4\r\nWiki\r\n7;ext=1\r\npedia i\r\nB\r\nn \r\nchunks.\r\n0\r\nExpires: never\r\n\r\nGET / HTTP/1.1\r\n";
+
+#[tokio::test]
+async fn test_chunked_read() {
+    for max in [1, 3, BODY.len()] {
+        let reader = AsyncBufReader::with_chunk_size(4, Trickle::new(BODY, max));
+        let mut decoder = ChunkedDecoder::new(reader);
+
+        let mut body = Vec::new();
+        decoder.read_to_end(&mut body).await.unwrap();
+        assert_eq!(body, b"Wikipedia in \r\nchunks.");
+        assert!(decoder.is_finished());
+        assert_eq!(
+            decoder.trailers().unwrap(),
+            [(Bytes::from("Expires"), Bytes::from("never"))]
+        );
+
+        // The inner reader is positioned after the body
+        let mut reader = decoder.into_inner();
+        assert_eq!(reader.peek_line(64).await.unwrap(), b"GET / HTTP/1.1\r\n");
+    }
+}
+
+#[tokio::test]
+async fn test_chunked_buf_read() {
+    let reader = AsyncBufReader::new(BODY);
+    let mut decoder = ChunkedDecoder::new(reader);
+    assert!(decoder.trailers().is_none());
+
+    // Reads within a chunk are not copied
+    assert_eq!(decoder.peek(2).await.unwrap(), b"Wi");
+    assert_eq!(decoder.read_bytes(4).await.unwrap(), "Wiki");
+
+    // Reads spanning chunks are coalesced
+    assert_eq!(decoder.peek_exact(10).await.unwrap(), b"pedia in \r");
+    assert_eq!(decoder.read_bytes(10).await.unwrap(), "pedia in \r");
+    assert_eq!(decoder.take_buffered(), "\nchunks.");
+    assert_eq!(decoder.peek(8).await.unwrap(), b"");
+    assert!(decoder.ended());
+}
+
+#[tokio::test]
+async fn test_chunked_errors() {
+    let cases: [(&[u8], ChunkedError); 4] = [
+        (b"x\r\n", ChunkedError::InvalidSize),
+        (b"4 x\r\n", ChunkedError::InvalidSize),
+        (b"1\r\nab\r\n", ChunkedError::InvalidDelimiter),
+        (b"0\r\nExpires\r\n\r\n", ChunkedError::InvalidTrailer),
+    ];
+    for (body, expected) in cases {
+        let mut decoder = ChunkedDecoder::new(body);
+        let err = decoder.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let inner_err = err.get_ref().unwrap().downcast_ref::<ChunkedError>();
+        assert_eq!(inner_err, Some(&expected));
+    }
+
+    let body: &[u8] = b"1;a=bcdef\r\na\r\n1;ghijk\r\nb\r\n0\r\n\r\n";
+    let mut decoder = ChunkedDecoder::new(body).with_max_extensions_size(12);
+    let err = decoder.read_to_end(&mut Vec::new()).await.unwrap_err();
+    let inner_err = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(inner_err, Some(&LimitExceeded { limit: 12 }));
+
+    let body: &[u8] = b"00000000000000001\r\na\r\n0\r\n\r\n";
+    let mut decoder = ChunkedDecoder::new(body).with_max_line_length(8);
+    let err = decoder.read_to_end(&mut Vec::new()).await.unwrap_err();
+    let inner_err = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(inner_err, Some(&LimitExceeded { limit: 8 }));
+
+    let mut decoder = ChunkedDecoder::new(&b"4\r\nWi"[..]);
+    let err = decoder.read_to_end(&mut Vec::new()).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "codec")]
+
+use std::io;
+
+use async_buf_read::codec::FramedRead;
+use async_buf_read::{AsyncBufPassthrough, AsyncBufReadExt, AsyncBufReader, BufferOverflow};
+use bytes::BytesMut;
+use futures::StreamExt;
+use tokio_util::codec::{Decoder, LinesCodec, LinesCodecError};
+
+use self::common::{AsyncReadExt, Trickle};
+
+mod common;
+
+/// A decoder that doubles every byte in place, then returns frames of 4 bytes.
+#[derive(Default)]
+struct Doubling {
+    expanded: usize,
+}
+
+impl Decoder for Doubling {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        let rest = src.split_off(self.expanded);
+        for byte in rest {
+            src.extend_from_slice(&[byte, byte]);
+        }
+        if src.len() < 4 {
+            self.expanded = src.len();
+            return Ok(None);
+        }
+        self.expanded = src.len() - 4;
+        Ok(Some(src.split_to(4)))
+    }
+}
+
+#[tokio::test]
+async fn test_framed_read() {
+    let inner: &[u8] = b"HELLO\r\nVERSION 2\r\n\x00\x01binary";
+    let reader = AsyncBufReader::with_chunk_size(4, Trickle::new(inner, 3));
+    let mut framed = FramedRead::new(reader, LinesCodec::new());
+
+    assert_eq!(framed.next().await.unwrap().unwrap(), "HELLO");
+    assert_eq!(framed.next().await.unwrap().unwrap(), "VERSION 2");
+
+    // The leftover data stays in the reader
+    let mut reader = framed.into_inner();
+    assert_eq!(reader.peek(2).await.unwrap(), [0x00, 0x01]);
+    reader.passthrough(true);
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, b"\x00\x01binary");
+}
+
+#[tokio::test]
+async fn test_framed_read_eof() {
+    let inner: &[u8] = b"first\nlast";
+    let mut framed = FramedRead::new(AsyncBufReader::new(inner), LinesCodec::new());
+    assert_eq!(framed.next().await.unwrap().unwrap(), "first");
+    assert_eq!(framed.next().await.unwrap().unwrap(), "last");
+    assert!(framed.next().await.is_none());
+
+    let inner: &[u8] = b"a very long line\n";
+    let codec = LinesCodec::new_with_max_length(4);
+    let mut framed = FramedRead::new(AsyncBufReader::new(inner), codec);
+    let err = framed.next().await.unwrap().unwrap_err();
+    assert!(matches!(err, LinesCodecError::MaxLineLengthExceeded));
+}
+
+#[tokio::test]
+async fn test_framed_read_passthrough() {
+    let inner: &[u8] = b"first\nsecond\n";
+    let mut framed = FramedRead::new(AsyncBufReader::new(inner), LinesCodec::new());
+
+    // A checkpoint makes the stream fail instead of panicking
+    let checkpoint = framed.get_mut().checkpoint();
+    assert!(framed.next().await.unwrap().is_err());
+    framed.get_mut().commit(checkpoint);
+
+    // In passthrough mode the stream ends without reaching EOF
+    framed.passthrough(true);
+    assert!(framed.next().await.is_none());
+    framed.passthrough(false);
+    assert_eq!(framed.next().await.unwrap().unwrap(), "first");
+    assert_eq!(framed.next().await.unwrap().unwrap(), "second");
+    assert!(framed.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_framed_read_buffer_overflow() {
+    let inner: &[u8] = b"abcdefghijkl";
+    let reader = AsyncBufReader::new(inner).with_max_buffer_size(16);
+    let mut framed = FramedRead::new(reader, Doubling::default());
+
+    // The decoder grows the buffer to 20 bytes
+    let err = framed.next().await.unwrap().unwrap_err();
+    let overflow = err.get_ref().unwrap().downcast_ref::<BufferOverflow>();
+    assert_eq!(
+        overflow,
+        Some(&BufferOverflow {
+            requested: 20,
+            max_buffer_size: 16
+        })
+    );
+
+    let inner: &[u8] = b"abcdef";
+    let reader = AsyncBufReader::new(inner).with_max_buffer_size(16);
+    let mut framed = FramedRead::new(reader, Doubling::default());
+    assert_eq!(framed.next().await.unwrap().unwrap(), "aabb");
+    assert_eq!(framed.next().await.unwrap().unwrap(), "ccdd");
+    assert_eq!(framed.next().await.unwrap().unwrap(), "eeff");
+}
@@ -0,0 +1,264 @@
+#![allow(dead_code, unused_imports)]
+
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+pub use futures::io::{AsyncBufReadExt as RuntimeBufReadExt, BufReader};
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+pub use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+#[cfg(feature = "tokio")]
+pub use tokio::io::{AsyncBufReadExt as RuntimeBufReadExt, BufReader};
+#[cfg(feature = "tokio")]
+pub use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// Polls a read on the runtime reader.
+///
+/// This hides the difference between the `tokio` and `futures` read signatures
+/// so the mock readers can be written once.
+#[cfg(feature = "tokio")]
+fn poll_read_slice<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+) -> Poll<io::Result<()>> {
+    Pin::new(reader).poll_read(cx, buf)
+}
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+fn poll_read_slice<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+) -> Poll<io::Result<usize>> {
+    Pin::new(reader).poll_read(cx, buf)
+}
+
+/// Implements the runtime `AsyncRead` trait for a mock reader that
+/// provides an inherent `poll_mock` method.
+macro_rules! impl_mock_read {
+    ($ty:ident) => {
+        #[cfg(feature = "tokio")]
+        impl AsyncRead for $ty<'_> {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut ReadBuf<'_>,
+            ) -> Poll<io::Result<()>> {
+                self.get_mut()
+                    .poll_mock(cx, |inner, cx| poll_read_slice(inner, cx, buf))
+            }
+        }
+
+        #[cfg(all(feature = "futures", not(feature = "tokio")))]
+        impl AsyncRead for $ty<'_> {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                self.get_mut()
+                    .poll_mock(cx, |inner, cx| poll_read_slice(inner, cx, buf))
+            }
+        }
+    };
+}
+
+/// A reader that alternates between pending and ready.
+pub struct MaybePending<'a> {
+    inner: &'a [u8],
+    ready_read: bool,
+}
+
+impl<'a> MaybePending<'a> {
+    pub fn new(inner: &'a [u8]) -> Self {
+        Self {
+            inner,
+            ready_read: false,
+        }
+    }
+
+    fn poll_mock<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        read: impl FnOnce(&mut &'a [u8], &mut Context<'_>) -> Poll<T>,
+    ) -> Poll<T> {
+        if self.ready_read {
+            self.ready_read = false;
+            read(&mut self.inner, cx)
+        } else {
+            self.ready_read = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+impl_mock_read!(MaybePending);
+
+/// A reader that is ready once and then always pending.
+pub struct AlwaysPending<'a> {
+    inner: &'a [u8],
+    ready_read: bool,
+}
+
+impl<'a> AlwaysPending<'a> {
+    pub fn new(inner: &'a [u8]) -> Self {
+        Self {
+            inner,
+            ready_read: true,
+        }
+    }
+
+    fn poll_mock<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        read: impl FnOnce(&mut &'a [u8], &mut Context<'_>) -> Poll<T>,
+    ) -> Poll<T> {
+        if self.ready_read {
+            self.ready_read = false;
+            read(&mut self.inner, cx)
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+impl_mock_read!(AlwaysPending);
+
+/// A reader that returns at most `max` bytes per read.
+pub struct Trickle<'a> {
+    inner: &'a [u8],
+    max: usize,
+}
+
+impl<'a> Trickle<'a> {
+    pub fn new(inner: &'a [u8], max: usize) -> Self {
+        Self { inner, max }
+    }
+
+    fn poll_mock<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        read: impl FnOnce(&mut &'a [u8], &mut Context<'_>) -> Poll<T>,
+    ) -> Poll<T> {
+        let amt = std::cmp::min(self.max, self.inner.len());
+        let mut head = &self.inner[..amt];
+        let res = read(&mut head, cx);
+        self.inner = &self.inner[amt - head.len()..];
+        res
+    }
+}
+
+impl_mock_read!(Trickle);
+
+/// A stream that reads from a slice and records every write call.
+pub struct Recorder<'a> {
+    inner: &'a [u8],
+    vectored: bool,
+    pub writes: Vec<Vec<u8>>,
+    pub flushed: bool,
+}
+
+impl<'a> Recorder<'a> {
+    pub fn new(inner: &'a [u8], vectored: bool) -> Self {
+        Self {
+            inner,
+            vectored,
+            writes: Vec::new(),
+            flushed: false,
+        }
+    }
+
+    pub fn written(&self) -> Vec<u8> {
+        self.writes.concat()
+    }
+
+    fn poll_mock<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        read: impl FnOnce(&mut &'a [u8], &mut Context<'_>) -> Poll<T>,
+    ) -> Poll<T> {
+        read(&mut self.inner, cx)
+    }
+
+    fn record(&mut self, bufs: &[IoSlice<'_>]) -> Poll<io::Result<usize>> {
+        let data = if self.vectored {
+            bufs.iter().flat_map(|buf| buf.iter().copied()).collect()
+        } else {
+            bufs.iter()
+                .find(|buf| !buf.is_empty())
+                .map_or_else(Vec::new, |buf| buf.to_vec())
+        };
+        let n = data.len();
+        self.writes.push(data);
+        self.flushed = false;
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl_mock_read!(Recorder);
+
+#[cfg(feature = "tokio")]
+impl AsyncWrite for Recorder<'_> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().record(&[IoSlice::new(buf)])
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().record(bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.vectored
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().flushed = true;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(all(feature = "futures", not(feature = "tokio")))]
+impl AsyncWrite for Recorder<'_> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().record(&[IoSlice::new(buf)])
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().record(bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().flushed = true;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
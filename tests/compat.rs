@@ -0,0 +1,85 @@
+use async_buf_read::compat::{Compat, RuntimeCompat};
+use std::pin::Pin;
+
+use async_buf_read::{AsyncBufPassthrough, AsyncBufRead, AsyncBufReadExt, AsyncBufReader};
+
+use self::common::{AsyncReadExt, BufReader, RuntimeBufReadExt, Trickle};
+
+mod common;
+
+#[tokio::test]
+async fn test_buf_reader_runtime_buf_read() {
+    let mut reader = AsyncBufReader::with_chunk_size(4, Trickle::new(b"hello\nworld\n!", 3));
+    assert_eq!(reader.peek_exact(2).await.unwrap(), b"he");
+
+    let mut line = String::new();
+    RuntimeBufReadExt::read_line(&mut reader, &mut line)
+        .await
+        .unwrap();
+    assert_eq!(line, "hello\n");
+
+    // The buffer is filled even in passthrough mode
+    reader.passthrough(true);
+    line.clear();
+    RuntimeBufReadExt::read_line(&mut reader, &mut line)
+        .await
+        .unwrap();
+    assert_eq!(line, "world\n");
+    line.clear();
+    RuntimeBufReadExt::read_line(&mut reader, &mut line)
+        .await
+        .unwrap();
+    assert_eq!(line, "!");
+}
+
+#[tokio::test]
+async fn test_compat_inner_buffer() {
+    let inner = BufReader::with_capacity(8, Trickle::new(b"hello world!", 8));
+    let mut reader = Compat::new(inner);
+
+    // Requests covered by the inner buffer are served from it
+    let peeked = reader.peek_exact(4).await.unwrap().as_ptr();
+    assert_eq!(peeked, reader.get_ref().buffer().as_ptr());
+    assert_eq!(Pin::new(&reader).buf(), b"hello wo");
+    assert_eq!(reader.read_bytes(6).await.unwrap(), "hello ");
+    assert_eq!(reader.get_ref().buffer(), b"wo");
+
+    // Larger requests are copied once the inner buffer runs out
+    assert_eq!(reader.peek_exact(4).await.unwrap(), b"worl");
+    assert_eq!(reader.get_ref().buffer(), b"d!");
+    assert_eq!(Pin::new(&reader).buf(), b"worl");
+    reader.consume(4);
+    assert_eq!(Pin::new(&reader).buf(), b"d!");
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"d!");
+}
+
+#[tokio::test]
+async fn test_compat() {
+    let inner = BufReader::with_capacity(4, Trickle::new(b"hello world!", 3));
+    let mut reader = Compat::new(inner);
+
+    // Requests span several reads of the inner reader
+    assert_eq!(reader.peek_exact(8).await.unwrap(), b"hello wo");
+    reader.consume(6);
+    assert_eq!(reader.read_bytes(3).await.unwrap(), "wor");
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"ld!");
+    assert!(reader.peek_exact(1).await.is_err());
+
+    // The other direction
+    let mut inner = &b"hello\nworld\n!"[..];
+    let mut reader = RuntimeCompat::new(AsyncBufReadExt::take(&mut inner, 9));
+    let mut line = String::new();
+    RuntimeBufReadExt::read_line(&mut reader, &mut line)
+        .await
+        .unwrap();
+    assert_eq!(line, "hello\n");
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"wor");
+}
@@ -0,0 +1,147 @@
+#![cfg(all(
+    feature = "compression-gzip",
+    feature = "compression-deflate",
+    feature = "compression-brotli",
+    feature = "compression-zstd"
+))]
+
+use std::io::{self, Write};
+
+use async_buf_read::compression::{DecompressError, Decompressor, Encoding};
+use async_buf_read::{AsyncBufReadExt, AsyncBufReader, LimitExceeded, UnexpectedEof};
+use flate2::Compression;
+
+use self::common::{AsyncReadExt, Trickle};
+
+mod common;
+
+fn content() -> Vec<u8> {
+    (0..20_000u32)
+        .flat_map(|i| format!("line {}\n", i % 1000).into_bytes())
+        .collect()
+}
+
+fn compress(encoding: &str, data: &[u8]) -> Vec<u8> {
+    let level = Compression::default();
+    match encoding {
+        "gzip" => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+        "zlib" => {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), level);
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+        "deflate" => {
+            let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), level);
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+        "br" => {
+            let mut compressed = Vec::new();
+            let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+            encoder.write_all(data).unwrap();
+            drop(encoder);
+            compressed
+        }
+        "zstd" => zstd::encode_all(data, 3).unwrap(),
+        _ => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn test_decompress() {
+    let content = content();
+    let cases = [
+        ("gzip", Encoding::Gzip),
+        ("zlib", Encoding::Deflate),
+        ("deflate", Encoding::Deflate),
+        ("br", Encoding::Brotli),
+        ("zstd", Encoding::Zstd),
+    ];
+    for (name, encoding) in cases {
+        let mut compressed = compress(name, &content);
+        compressed.extend_from_slice(b"next");
+        for max in [1, 7, compressed.len()] {
+            let reader = AsyncBufReader::with_chunk_size(64, Trickle::new(&compressed, max));
+            let mut decoder = Decompressor::new(encoding, reader);
+            assert_eq!(decoder.peek_exact(12).await.unwrap(), b"line 0\nline ");
+            assert_eq!(decoder.read_bytes(7).await.unwrap(), "line 0\n");
+
+            let mut decompressed = Vec::new();
+            decoder.read_to_end(&mut decompressed).await.unwrap();
+            assert_eq!(decompressed, content[7..], "{name}");
+            assert!(decoder.is_finished());
+
+            // The data following the stream is left in the inner reader
+            let mut reader = decoder.into_inner();
+            assert_eq!(reader.peek_exact(4).await.unwrap(), b"next");
+        }
+    }
+    assert_eq!(Encoding::from_name(b"X-Gzip"), Some(Encoding::Gzip));
+    assert_eq!(Encoding::from_name(b"identity"), None);
+}
+
+#[tokio::test]
+async fn test_decompress_limit() {
+    let compressed = compress("gzip", &content());
+    let mut decoder = Decompressor::new(Encoding::Gzip, AsyncBufReader::new(&compressed[..]))
+        .with_max_decompressed_size(1000);
+    assert_eq!(decoder.peek_exact(1000).await.unwrap().len(), 1000);
+    let err = decoder.peek_exact(1001).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(err, Some(&LimitExceeded { limit: 1000 }));
+}
+
+#[tokio::test]
+async fn test_decompress_errors() {
+    let compressed = compress("gzip", b"hello");
+
+    // Invalid header
+    let mut decoder = Decompressor::new(Encoding::Gzip, AsyncBufReader::new(&compressed[1..]));
+    let err = decoder.peek(1).await.unwrap_err();
+    let err = err.get_ref().unwrap().downcast_ref::<DecompressError>();
+    assert_eq!(err, Some(&DecompressError::InvalidHeader));
+
+    // A file name without end
+    let mut header = vec![0x1f, 0x8b, 0x08, 0x08, 0, 0, 0, 0, 0, 0xff];
+    header.resize(128 * 1024, b'a');
+    let mut decoder = Decompressor::new(Encoding::Gzip, AsyncBufReader::new(&header[..]));
+    let err = decoder.peek(1).await.unwrap_err();
+    let err = err.get_ref().unwrap().downcast_ref::<DecompressError>();
+    assert_eq!(err, Some(&DecompressError::InvalidHeader));
+
+    // Invalid checksum
+    let mut corrupted = compressed.clone();
+    let len = corrupted.len();
+    corrupted[len - 8] ^= 0xff;
+    let mut decoder = Decompressor::new(Encoding::Gzip, AsyncBufReader::new(&corrupted[..]));
+    let mut decompressed = Vec::new();
+    let err = decoder.read_to_end(&mut decompressed).await.unwrap_err();
+    let err = err.get_ref().unwrap().downcast_ref::<DecompressError>();
+    assert_eq!(err, Some(&DecompressError::InvalidTrailer));
+
+    // Invalid data
+    let mut decoder = Decompressor::new(Encoding::Zstd, AsyncBufReader::new(&b"garbage"[..]));
+    let err = decoder.peek(1).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // Truncated
+    let truncated = &compressed[..len - 4];
+    let mut decoder = Decompressor::new(Encoding::Gzip, AsyncBufReader::new(truncated));
+    let mut decompressed = Vec::new();
+    let err = decoder.read_to_end(&mut decompressed).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let err = err.get_ref().unwrap().downcast_ref::<UnexpectedEof>();
+    assert_eq!(
+        err,
+        Some(&UnexpectedEof {
+            requested: 5,
+            partial: 4
+        })
+    );
+    assert_eq!(decompressed, b"hello");
+}
@@ -0,0 +1,99 @@
+use std::io;
+
+use async_buf_read::framing::{FrameError, LengthDelimited, LengthFormat};
+use async_buf_read::{AsyncBufPassthrough, AsyncBufReader, LimitExceeded, UnexpectedEof};
+
+use self::common::{AsyncReadExt, Trickle};
+
+mod common;
+
+#[tokio::test]
+async fn test_length_delimited() {
+    let cases: [(LengthFormat, &[u8]); 7] = [
+        (LengthFormat::U8, b"\x03abc\x00\x02de"),
+        (LengthFormat::U16Be, b"\x00\x03abc\x00\x00\x00\x02de"),
+        (LengthFormat::U16Le, b"\x03\x00abc\x00\x00\x02\x00de"),
+        (
+            LengthFormat::U32Be,
+            b"\x00\x00\x00\x03abc\x00\x00\x00\x00\x00\x00\x00\x02de",
+        ),
+        (
+            LengthFormat::U32Le,
+            b"\x03\x00\x00\x00abc\x00\x00\x00\x00\x02\x00\x00\x00de",
+        ),
+        (LengthFormat::Varint, b"\x03abc\x00\x02de"),
+        (LengthFormat::Netstring, b"3:abc,0:,2:de,"),
+    ];
+    for (format, inner) in cases {
+        let reader = AsyncBufReader::with_chunk_size(2, Trickle::new(inner, 1));
+        let mut framing = LengthDelimited::new(format, reader);
+        assert_eq!(framing.read_frame().await.unwrap().unwrap(), "abc");
+        assert_eq!(framing.read_frame().await.unwrap().unwrap(), "");
+        assert_eq!(framing.read_frame().await.unwrap().unwrap(), "de");
+        assert_eq!(framing.read_frame().await.unwrap(), None);
+    }
+
+    let inner: &[u8] = b"\xac\x02";
+    let mut framing = LengthDelimited::new(LengthFormat::Varint, inner);
+    let err = framing.read_frame().await.unwrap_err();
+    let inner_err = err.get_ref().unwrap().downcast_ref::<UnexpectedEof>();
+    assert_eq!(
+        inner_err,
+        Some(&UnexpectedEof {
+            requested: 302,
+            partial: 2
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_length_delimited_passthrough() {
+    let inner: &[u8] = b"\x00\x02hi\x00\x03abcrest";
+    let reader = AsyncBufReader::with_chunk_size(9, inner);
+    let mut framing = LengthDelimited::new(LengthFormat::U16Be, reader);
+
+    assert_eq!(framing.read_frame().await.unwrap().unwrap(), "hi");
+    assert_eq!(framing.read_frame().await.unwrap().unwrap(), "abc");
+
+    // The frames don't end when the buffer stops growing
+    framing.passthrough(true);
+    let err = framing.read_frame().await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    let mut rest = Vec::new();
+    framing.get_mut().read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, b"rest");
+}
+
+#[tokio::test]
+async fn test_length_delimited_errors() {
+    let inner: &[u8] = b"\x00\x00\x01\x00";
+    let mut framing = LengthDelimited::new(LengthFormat::U32Be, inner).with_max_frame_size(255);
+    let err = framing.read_frame().await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let inner_err = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(inner_err, Some(&LimitExceeded { limit: 255 }));
+    assert_eq!(framing.get_ref().len(), 4);
+
+    let cases: [&[u8]; 4] = [b"03:abc,", b":abc,", b"3:abc;", b"3x"];
+    for inner in cases {
+        let mut framing = LengthDelimited::new(LengthFormat::Netstring, inner);
+        let err = framing.read_frame().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.get_ref().unwrap().is::<FrameError>());
+        assert_eq!(*framing.get_ref(), inner);
+    }
+
+    let inner: &[u8] = b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x02";
+    let mut framing = LengthDelimited::new(LengthFormat::Varint, inner);
+    let err = framing.read_frame().await.unwrap_err();
+    let inner_err = err.get_ref().unwrap().downcast_ref::<FrameError>();
+    assert_eq!(inner_err, Some(&FrameError::InvalidHeader));
+
+    // The length of the header and the frame overflows
+    let inner: &[u8] = b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01";
+    let mut framing =
+        LengthDelimited::new(LengthFormat::Varint, inner).with_max_frame_size(usize::MAX);
+    let err = framing.read_frame().await.unwrap_err();
+    let inner_err = err.get_ref().unwrap().downcast_ref::<FrameError>();
+    assert_eq!(inner_err, Some(&FrameError::InvalidHeader));
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "http")]
+
+use std::io;
+
+use async_buf_read::http::{HeadLimits, ParseError};
+use async_buf_read::{AsyncBufReadExt, AsyncBufReader, LimitExceeded, UnexpectedEof};
+
+use self::common::{AsyncReadExt, Trickle};
+
+mod common;
+
+const REQUEST: &[u8] =
+    b"GET /index.html HTTP/1.1\r\nHost: example.com\r\nX-Empty:\r\nhost: other\r\n\r\nbody";
+
+const RESPONSE: &[u8] = b"HTTP/1.0 404 Not Found\r\nContent-Length: 4\r\n\r\nbody";
+
+#[tokio::test]
+async fn test_read_request_head() {
+    for max in [1, 7, REQUEST.len()] {
+        let mut reader = AsyncBufReader::with_chunk_size(4, Trickle::new(REQUEST, max));
+        let head = reader
+            .read_request_head(HeadLimits::default())
+            .await
+            .unwrap();
+        assert_eq!(head.method, "GET");
+        assert_eq!(head.path, "/index.html");
+        assert_eq!(head.minor_version, 1);
+        assert_eq!(head.headers.len(), 3);
+        assert_eq!(head.headers[0].name, "Host");
+        assert_eq!(head.headers[1].value, "");
+        assert_eq!(head.header("HOST").unwrap(), "example.com");
+        assert_eq!(head.header("Missing"), None);
+
+        // Only the head was consumed
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).await.unwrap();
+        assert_eq!(body, b"body");
+    }
+}
+
+#[tokio::test]
+async fn test_read_response_head() {
+    let mut reader = AsyncBufReader::with_chunk_size(4, Trickle::new(RESPONSE, 5));
+    let head = reader
+        .read_response_head(HeadLimits::default())
+        .await
+        .unwrap();
+    assert_eq!(head.minor_version, 0);
+    assert_eq!(head.code, 404);
+    assert_eq!(head.reason, "Not Found");
+    assert_eq!(head.header("content-length").unwrap(), "4");
+    assert_eq!(reader.peek_exact(4).await.unwrap(), b"body");
+}
+
+#[tokio::test]
+async fn test_read_head_errors() {
+    // Too large
+    let mut reader = AsyncBufReader::new(REQUEST);
+    let limits = HeadLimits {
+        max_size: 32,
+        ..HeadLimits::default()
+    };
+    let err = reader.read_request_head(limits).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(err, Some(&LimitExceeded { limit: 32 }));
+    assert_eq!(reader.peek(REQUEST.len()).await.unwrap(), REQUEST);
+
+    // Too many headers
+    let limits = HeadLimits {
+        max_headers: 2,
+        ..HeadLimits::default()
+    };
+    let err = reader.read_request_head(limits).await.unwrap_err();
+    let err = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(err, Some(&LimitExceeded { limit: 2 }));
+
+    // Malformed
+    let mut reader = AsyncBufReader::new(&b"HTTP/1.1 200 OK\r\n\r\n"[..]);
+    let err = reader
+        .read_request_head(HeadLimits::default())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = err.get_ref().unwrap().downcast_ref::<ParseError>();
+    assert_eq!(err, Some(&ParseError::Token));
+    assert_eq!(reader.buffer().len(), 19);
+
+    // Incomplete
+    let mut reader = AsyncBufReader::new(&RESPONSE[..20]);
+    let err = reader
+        .read_response_head(HeadLimits::default())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let err = err.get_ref().unwrap().downcast_ref::<UnexpectedEof>();
+    assert_eq!(
+        err,
+        Some(&UnexpectedEof {
+            requested: 21,
+            partial: 20
+        })
+    );
+}
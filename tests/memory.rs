@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+use std::io;
+
+use async_buf_read::{AsyncBufReadExt, InMemory};
+use bytes::Bytes;
+
+use self::common::AsyncReadExt;
+
+mod common;
+
+#[tokio::test]
+async fn test_slice_eof() {
+    let mut reader: &[u8] = &[5, 6, 7, 0];
+    assert!(!reader.ended());
+
+    let buf = reader.peek(8).await.unwrap();
+    assert_eq!(buf, [5, 6, 7, 0]);
+    reader.consume(4);
+    assert!(reader.ended());
+
+    let err = reader.peek_exact(1).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_cursor() {
+    let mut reader = std::io::Cursor::new(vec![5, 6, 7, 0, 1, 2]);
+    reader.set_position(1);
+    assert_eq!(reader.buffer(), [6, 7, 0, 1, 2]);
+
+    let buf = reader.peek_exact(3).await.unwrap();
+    assert_eq!(buf, [6, 7, 0]);
+    reader.consume(3);
+    assert_eq!(reader.position(), 4);
+
+    let bytes = reader.read_bytes(2).await.unwrap();
+    assert_eq!(bytes, [1, 2].as_slice());
+    assert!(reader.ended());
+
+    // Positions past the end are treated as EOF
+    reader.set_position(10);
+    assert!(reader.ended());
+    assert_eq!(reader.peek(4).await.unwrap(), []);
+}
+
+#[tokio::test]
+async fn test_bytes() {
+    let mut reader = InMemory::new(Bytes::from_static(b"GET / HTTP/1.1\r\nHost: caido.io\r\n"));
+
+    let line = reader.peek_line(64).await.unwrap();
+    assert_eq!(line, b"GET / HTTP/1.1\r\n");
+
+    let bytes = reader.read_bytes(16).await.unwrap();
+    assert_eq!(bytes, b"GET / HTTP/1.1\r\n".as_slice());
+    assert!(!reader.ended());
+
+    let rest = reader.take_buffered();
+    assert_eq!(rest, b"Host: caido.io\r\n".as_slice());
+    assert!(reader.ended());
+}
+
+#[tokio::test]
+async fn test_vec_deque() {
+    let mut data = VecDeque::with_capacity(8);
+    data.extend([0, 0, 0, 0, 0, 0, 5, 6]);
+    data.drain(..6);
+    data.extend([7, 0, 1, 2, 3, 4]);
+    assert!(data.as_slices().0.len() < 8);
+    let mut reader = InMemory::new(data);
+
+    let buf = reader.peek_exact(8).await.unwrap();
+    assert_eq!(buf, [5, 6, 7, 0, 1, 2, 3, 4]);
+    assert_eq!(reader.buffer(), [5, 6, 7, 0, 1, 2, 3, 4]);
+
+    reader.consume(6);
+    assert_eq!(reader.buffer(), [3, 4]);
+    assert!(!reader.ended());
+
+    reader.consume(2);
+    assert!(reader.ended());
+}
+
+#[tokio::test]
+async fn test_in_memory_read() {
+    let mut reader = InMemory::new(Bytes::from_static(b"hello"));
+    assert_eq!(reader.peek(2).await.unwrap(), b"he");
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"hello");
+    assert!(reader.ended());
+}
@@ -0,0 +1,114 @@
+use std::io;
+
+use async_buf_read::proxy::{
+    self, ProxyAddresses, ProxyCommand, ProxyError, ProxyHeader, ProxyTransport, Tlv,
+};
+use async_buf_read::{AsyncBufPassthrough, AsyncBufReadExt, AsyncBufReader};
+use bytes::Bytes;
+
+use self::common::{AsyncReadExt, Trickle};
+
+mod common;
+
+#[tokio::test]
+async fn test_proxy_v1() {
+    let inner: &[u8] = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET / HTTP/1.1\r\n";
+    let mut reader = AsyncBufReader::new(Trickle::new(inner, 5));
+
+    let header = proxy::read_header(&mut reader).await.unwrap();
+    assert_eq!(
+        header,
+        ProxyHeader {
+            version: 1,
+            command: ProxyCommand::Proxy,
+            transport: ProxyTransport::Stream,
+            addresses: ProxyAddresses::Inet {
+                source: "192.168.0.1:56324".parse().unwrap(),
+                destination: "192.168.0.11:443".parse().unwrap(),
+            },
+            tlvs: Vec::new(),
+        }
+    );
+
+    // The payload is still readable in passthrough mode
+    reader.passthrough(true);
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, b"GET / HTTP/1.1\r\n");
+
+    let mut reader: &[u8] = b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\npayload";
+    let header = proxy::read_header(&mut reader).await.unwrap();
+    assert_eq!(header.addresses, ProxyAddresses::Unknown);
+    assert_eq!(reader, b"payload");
+}
+
+#[tokio::test]
+async fn test_proxy_v2() {
+    let mut inner = b"\r\n\r\n\0\r\nQUIT\n\x21\x21".to_vec();
+    inner.extend(44u16.to_be_bytes());
+    inner.extend([0x20, 0x01, 0x0d, 0xb8].iter().chain(&[0; 11]).chain(&[1]));
+    inner.extend([0x20, 0x01, 0x0d, 0xb8].iter().chain(&[0; 11]).chain(&[2]));
+    inner.extend(1234u16.to_be_bytes());
+    inner.extend(443u16.to_be_bytes());
+    inner.extend([0x01, 0x00, 0x02]);
+    inner.extend(b"h2");
+    inner.extend([0x04, 0x00, 0x00]);
+    inner.extend(b"payload");
+    let mut reader = AsyncBufReader::with_chunk_size(8, Trickle::new(&inner, 3));
+
+    let header = proxy::read_header(&mut reader).await.unwrap();
+    assert_eq!(
+        header,
+        ProxyHeader {
+            version: 2,
+            command: ProxyCommand::Proxy,
+            transport: ProxyTransport::Stream,
+            addresses: ProxyAddresses::Inet {
+                source: "[2001:db8::1]:1234".parse().unwrap(),
+                destination: "[2001:db8::2]:443".parse().unwrap(),
+            },
+            tlvs: vec![
+                Tlv {
+                    kind: 0x01,
+                    value: Bytes::from_static(b"h2"),
+                },
+                Tlv {
+                    kind: 0x04,
+                    value: Bytes::new(),
+                },
+            ],
+        }
+    );
+    let payload = reader.read_bytes(7).await.unwrap();
+    assert_eq!(payload, b"payload".as_slice());
+
+    let mut reader: &[u8] = b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00payload";
+    let header = proxy::read_header(&mut reader).await.unwrap();
+    assert_eq!(header.command, ProxyCommand::Local);
+    assert_eq!(header.addresses, ProxyAddresses::Unknown);
+    assert_eq!(reader, b"payload");
+}
+
+#[tokio::test]
+async fn test_proxy_errors() {
+    let mut reader: &[u8] = b"GET / HTTP/1.1\r\n";
+    let err = proxy::read_header(&mut reader).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let inner_err = err.get_ref().unwrap().downcast_ref::<ProxyError>();
+    assert_eq!(inner_err, Some(&ProxyError::MissingSignature));
+    assert_eq!(reader.len(), 16);
+
+    let mut reader: &[u8] = b"PROXY TCP4 192.168.0.1 192.168.0.11 056324 443\r\n";
+    let err = proxy::read_header(&mut reader).await.unwrap_err();
+    let inner_err = err.get_ref().unwrap().downcast_ref::<ProxyError>();
+    assert_eq!(inner_err, Some(&ProxyError::Invalid("invalid port")));
+
+    let mut reader: &[u8] = b"PROXY TCP4 192.168.0.1";
+    let err = proxy::read_header(&mut reader).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    let mut reader: &[u8] = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c\x7f\x00";
+    let err = proxy::read_header(&mut reader).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(reader.len(), 18);
+}
@@ -1,76 +1,16 @@
-use std::{
-    io,
-    pin::Pin,
-    task::{Context, Poll},
-};
+use std::io;
 
-use async_buf_read::{AsyncBufPassthrough, AsyncBufReadExt, AsyncBufReader};
-use futures::poll;
-use tokio::{
-    io::{AsyncRead, AsyncReadExt, ReadBuf},
-    pin,
+use async_buf_read::{
+    AsyncBufPassthrough, AsyncBufReadExt, AsyncBufReader, BufferOverflow, LimitExceeded,
+    LineEnding, Prefixed, ReaderParts, UnexpectedEof,
 };
+use bytes::BytesMut;
+use futures::{StreamExt, poll};
+use tokio::pin;
 
-struct MaybePending<'a> {
-    inner: &'a [u8],
-    ready_read: bool,
-}
-
-impl<'a> MaybePending<'a> {
-    fn new(inner: &'a [u8]) -> Self {
-        Self {
-            inner,
-            ready_read: false,
-        }
-    }
-}
-
-impl AsyncRead for MaybePending<'_> {
-    fn poll_read(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        buf: &mut ReadBuf<'_>,
-    ) -> Poll<io::Result<()>> {
-        if self.ready_read {
-            self.ready_read = false;
-            Pin::new(&mut self.inner).poll_read(cx, buf)
-        } else {
-            self.ready_read = true;
-            cx.waker().wake_by_ref();
-            Poll::Pending
-        }
-    }
-}
+use self::common::{AlwaysPending, AsyncReadExt, MaybePending, Trickle};
 
-struct AlwaysPending<'a> {
-    inner: &'a [u8],
-    ready_read: bool,
-}
-
-impl<'a> AlwaysPending<'a> {
-    fn new(inner: &'a [u8]) -> Self {
-        Self {
-            inner,
-            ready_read: true,
-        }
-    }
-}
-
-impl AsyncRead for AlwaysPending<'_> {
-    fn poll_read(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        buf: &mut ReadBuf<'_>,
-    ) -> Poll<io::Result<()>> {
-        if self.ready_read {
-            self.ready_read = false;
-            Pin::new(&mut self.inner).poll_read(cx, buf)
-        } else {
-            cx.waker().wake_by_ref();
-            Poll::Pending
-        }
-    }
-}
+mod common;
 
 #[tokio::test]
 async fn test_buf_reader_read_basic() {
@@ -291,3 +231,523 @@ async fn test_buf_reader_eof() {
     assert!(reader.ended());
     assert_eq!(reader.buffer(), [5, 6, 7, 0, 1, 2, 3, 4, 11, 10]);
 }
+
+#[tokio::test]
+async fn test_buf_reader_peek_exact() {
+    let buffer: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4, 11, 10];
+    let inner = Trickle::new(buffer, 3);
+    let mut reader = AsyncBufReader::with_chunk_size(4, inner);
+
+    // A single read only returns part of the data
+    let buf = reader.peek(8).await.unwrap();
+    assert_eq!(buf, [5, 6, 7]);
+
+    let buf = reader.peek_exact(8).await.unwrap();
+    assert_eq!(buf, [5, 6, 7, 0, 1, 2, 3, 4]);
+    assert_eq!(reader.buffer(), [5, 6, 7, 0, 1, 2, 3, 4]);
+
+    let buf = reader.peek_exact(10).await.unwrap();
+    assert_eq!(buf, [5, 6, 7, 0, 1, 2, 3, 4, 11, 10]);
+    assert!(!reader.ended());
+}
+
+#[tokio::test]
+async fn test_buf_reader_peek_exact_eof() {
+    let buffer: &[u8] = &[5, 6, 7, 0, 1];
+    let inner = MaybePending::new(buffer);
+    let mut reader = AsyncBufReader::with_chunk_size(2, inner);
+
+    let err = reader.peek_exact(8).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let eof = err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<UnexpectedEof>()
+        .unwrap();
+    assert_eq!(eof.requested, 8);
+    assert_eq!(eof.partial, 5);
+    assert!(reader.ended());
+
+    // The partial data is not lost
+    assert_eq!(reader.buffer(), [5, 6, 7, 0, 1]);
+    let buf = reader.peek_exact(5).await.unwrap();
+    assert_eq!(buf, [5, 6, 7, 0, 1]);
+}
+
+#[tokio::test]
+async fn test_buf_reader_peek_exact_passthrough() {
+    let inner: &[u8] = &[5, 6, 7, 0, 1];
+    let mut reader = AsyncBufReader::with_chunk_size(2, inner);
+    assert_eq!(reader.peek_exact(2).await.unwrap(), [5, 6]);
+
+    // The buffer doesn't grow in passthrough mode, but the stream is open
+    reader.passthrough(true);
+    let err = reader.peek_exact(4).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert!(!reader.ended());
+    assert_eq!(reader.buffer(), [5, 6]);
+
+    // In-memory readers fail at the end of their data
+    let mut inner = inner;
+    let mut take = AsyncBufReadExt::take(&mut inner, 8);
+    let err = take.peek_exact(6).await.unwrap_err();
+    let err = err.get_ref().unwrap().downcast_ref::<UnexpectedEof>();
+    assert_eq!(
+        err,
+        Some(&UnexpectedEof {
+            requested: 6,
+            partial: 5
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_buf_reader_max_buffer_size() {
+    let inner: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4, 11, 10];
+    let mut reader = AsyncBufReader::with_chunk_size(8, inner).with_max_buffer_size(6);
+    assert_eq!(reader.max_buffer_size(), Some(6));
+
+    // The chunk size is capped by the maximum buffer size
+    let buf = reader.peek(4).await.unwrap();
+    assert_eq!(buf, [5, 6, 7, 0]);
+    assert_eq!(reader.buffer(), [5, 6, 7, 0, 1, 2]);
+
+    let err = reader.peek(7).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+    let overflow = err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<BufferOverflow>()
+        .unwrap();
+    assert_eq!(overflow.requested, 7);
+    assert_eq!(overflow.max_buffer_size, 6);
+    assert_eq!(reader.buffer(), [5, 6, 7, 0, 1, 2]);
+
+    // Reads are still possible with large destination buffers
+    let mut buf = [0; 16];
+    let nread = reader.read(&mut buf).await.unwrap();
+    assert_eq!(nread, 6);
+    assert_eq!(buf[..6], [5, 6, 7, 0, 1, 2]);
+}
+
+#[tokio::test]
+async fn test_buf_reader_max_buffer_size_retained() {
+    let inner: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4, 11, 10];
+    let mut reader =
+        AsyncBufReader::with_chunk_size(8, Trickle::new(inner, 4)).with_max_buffer_size(6);
+    assert!(reader.capacity() <= 6);
+
+    // The data retained for a checkpoint counts against the limit
+    let checkpoint = reader.checkpoint();
+    assert_eq!(reader.read_bytes(4).await.unwrap(), [5, 6, 7, 0].as_slice());
+    let err = reader.peek_exact(4).await.unwrap_err();
+    let overflow = err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<BufferOverflow>()
+        .unwrap();
+    assert_eq!(overflow.requested, 8);
+    assert!(reader.unread(&[9; 3]).is_err());
+    reader.commit(checkpoint);
+    assert_eq!(reader.peek_exact(4).await.unwrap(), [1, 2, 3, 4]);
+    reader.unread(&[9, 9]).unwrap();
+    assert_eq!(reader.buffer(), [9, 9, 1, 2, 3, 4]);
+
+    // A buffer without room fails instead of reporting EOF
+    let mut reader = AsyncBufReader::new(inner).with_max_buffer_size(0);
+    let err = common::RuntimeBufReadExt::fill_buf(&mut reader)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+    assert!(!reader.ended());
+}
+
+#[tokio::test]
+async fn test_buf_reader_peek_until() {
+    let buffer: &[u8] = b"Host: caido.io\r\nAccept: */*\r\n\r\n";
+    let inner = Trickle::new(buffer, 4);
+    let mut reader = AsyncBufReader::with_chunk_size(4, inner);
+
+    let buf = reader.peek_until(b"\r\n", 64).await.unwrap();
+    assert_eq!(buf, b"Host: caido.io\r\n");
+    let len = buf.len();
+    reader.consume(len);
+
+    let buf = reader.peek_line(64).await.unwrap();
+    assert_eq!(buf, b"Accept: */*\r\n");
+    let len = buf.len();
+    reader.consume(len);
+
+    let buf = reader.peek_until([b'\n'], 64).await.unwrap();
+    assert_eq!(buf, b"\r\n");
+}
+
+#[tokio::test]
+async fn test_buf_reader_peek_until_errors() {
+    let buffer: &[u8] = b"GET / HTTP/1.1";
+    let inner = MaybePending::new(buffer);
+    let mut reader = AsyncBufReader::with_chunk_size(4, inner);
+
+    let err = reader.peek_line(8).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let limit = err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<LimitExceeded>()
+        .unwrap();
+    assert_eq!(limit.limit, 8);
+
+    let err = reader.peek_line(64).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let err = err.get_ref().unwrap().downcast_ref::<UnexpectedEof>();
+    assert_eq!(
+        err,
+        Some(&UnexpectedEof {
+            requested: 15,
+            partial: 14
+        })
+    );
+    assert_eq!(reader.buffer(), b"GET / HTTP/1.1");
+}
+
+#[tokio::test]
+async fn test_buf_reader_peek_until_passthrough() {
+    let inner: &[u8] = b"GET / HTTP/1.1\r\n";
+    let mut reader = AsyncBufReader::with_chunk_size(4, inner);
+    assert_eq!(reader.peek_exact(4).await.unwrap(), b"GET ");
+
+    // The buffer doesn't grow in passthrough mode, but the stream is open
+    reader.passthrough(true);
+    let err = reader.peek_line(64).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert!(!reader.ended());
+
+    reader.passthrough(false);
+    assert_eq!(reader.peek_line(64).await.unwrap(), inner);
+}
+
+#[tokio::test]
+async fn test_buf_reader_read_bytes() {
+    let buffer: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4, 11, 10];
+    let inner = Trickle::new(buffer, 3);
+    let mut reader = AsyncBufReader::with_chunk_size(8, inner);
+
+    let bytes = reader.read_bytes(4).await.unwrap();
+    assert_eq!(bytes, [5, 6, 7, 0].as_slice());
+    assert_eq!(reader.buffer(), [1, 2]);
+
+    let bytes = reader.take_buffered();
+    assert_eq!(bytes, [1, 2].as_slice());
+    assert_eq!(reader.buffer(), []);
+
+    let err = reader.read_bytes(5).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(reader.buffer(), [3, 4, 11, 10]);
+
+    let bytes = reader.read_bytes(4).await.unwrap();
+    assert_eq!(bytes, [3, 4, 11, 10].as_slice());
+}
+
+#[tokio::test]
+async fn test_buf_reader_checkpoint() {
+    let inner: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4, 11, 10];
+    let mut reader = AsyncBufReader::with_chunk_size(4, Trickle::new(inner, 4));
+
+    let checkpoint = reader.checkpoint();
+    let mut buf = [0; 6];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [5, 6, 7, 0, 1, 2]);
+    assert_eq!(reader.buffer(), [3, 4]);
+
+    // Rewinding returns the consumed data again
+    reader.rewind(checkpoint);
+    assert_eq!(reader.buffer(), [5, 6, 7, 0, 1, 2, 3, 4]);
+
+    let checkpoint = reader.checkpoint();
+    reader.consume(3);
+    let nested = reader.checkpoint();
+    let bytes = reader.read_bytes(3).await.unwrap();
+    assert_eq!(bytes, [0, 1, 2].as_slice());
+    reader.rewind(nested);
+    assert_eq!(reader.buffer(), [0, 1, 2, 3, 4]);
+
+    // Committing keeps the current position
+    reader.consume(2);
+    reader.commit(checkpoint);
+    assert_eq!(reader.buffer(), [2, 3, 4]);
+
+    let mut buf = [0; 16];
+    let nread = reader.read(&mut buf).await.unwrap();
+    assert_eq!(buf[..nread], [2, 3, 4]);
+    let nread = reader.read(&mut buf).await.unwrap();
+    assert_eq!(buf[..nread], [11, 10]);
+}
+
+#[tokio::test]
+async fn test_buf_reader_foreign_checkpoint() {
+    let inner: &[u8] = &[5, 6, 7, 0];
+    let mut reader = AsyncBufReader::new(inner);
+    let mut other = AsyncBufReader::new(inner);
+    reader.peek(4).await.unwrap();
+    reader.consume(2);
+
+    // A checkpoint at the same position of another reader is rejected
+    // without changing the position
+    let foreign = other.checkpoint();
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| reader.rewind(foreign)));
+    assert!(res.is_err());
+    assert_eq!(reader.buffer(), [7, 0]);
+    assert_eq!(reader.retained(), 2);
+}
+
+#[tokio::test]
+async fn test_buf_reader_into_parts() {
+    let inner: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4];
+    let mut reader = AsyncBufReader::with_chunk_size(4, inner).with_max_buffer_size(6);
+
+    reader.peek(6).await.unwrap();
+    reader.consume(2);
+    reader.passthrough(true);
+
+    let parts = reader.into_parts();
+    assert_eq!(parts.buffer, [7, 0, 1, 2].as_slice());
+    assert_eq!(parts.reader, [3, 4]);
+    assert_eq!(parts.chunk_size, 4);
+    assert_eq!(parts.max_buffer_size, Some(6));
+    assert!(parts.passthrough);
+    assert!(!parts.eof);
+
+    // The state survives a round trip
+    let mut reader = AsyncBufReader::from_parts(parts);
+    assert_eq!(reader.buffer(), [7, 0, 1, 2]);
+    assert_eq!(reader.peek(6).await.unwrap(), [7, 0, 1, 2]);
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, [7, 0, 1, 2, 3, 4]);
+
+    let parts = ReaderParts::new(inner, BytesMut::from(&[1, 2][..]), 8);
+    let mut reader = AsyncBufReader::from_parts(parts);
+    assert_eq!(
+        reader.peek(16).await.unwrap(),
+        [1, 2, 5, 6, 7, 0, 1, 2, 3, 4]
+    );
+    assert!(!reader.ended());
+    assert_eq!(reader.peek(16).await.unwrap().len(), 10);
+    assert!(reader.ended());
+
+    let reader = AsyncBufReader::from_parts(reader.into_parts());
+    assert!(reader.ended());
+    assert_eq!(reader.buffer(), [1, 2, 5, 6, 7, 0, 1, 2, 3, 4]);
+    assert_eq!(reader.into_inner(), []);
+}
+
+#[tokio::test]
+async fn test_buf_reader_upgrade() {
+    let inner: &[u8] = &[0x16, 5, 6, 7, 0, 1, 2, 3, 4];
+    let mut reader = AsyncBufReader::with_chunk_size(4, Trickle::new(inner, 4));
+
+    assert_eq!(reader.peek(1).await.unwrap(), [0x16]);
+    reader.consume(1);
+
+    // The new layer sees the buffered data first
+    let mut reader = reader.upgrade(|prefixed| {
+        assert_eq!(prefixed.prefix(), [5, 6, 7]);
+        prefixed
+    });
+    assert!(reader.is_empty());
+    assert_eq!(reader.peek(5).await.unwrap(), [5, 6, 7]);
+    assert_eq!(reader.peek(5).await.unwrap(), [5, 6, 7, 0, 1]);
+
+    // The buffer stays in front of the new reader
+    assert_eq!(reader.buffer(), [5, 6, 7, 0, 1, 2, 3]);
+    let mut reader = reader.map_inner(|inner| Prefixed::new(&[9][..], inner));
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, [5, 6, 7, 0, 1, 2, 3, 9, 4]);
+
+    let mut prefixed = AsyncBufReader::new(inner).into_prefixed();
+    let mut buf = Vec::new();
+    prefixed.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, inner);
+}
+
+#[tokio::test]
+async fn test_buf_reader_unread() {
+    let inner: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4];
+    let mut reader = AsyncBufReader::with_prefix(&[1, 2], Trickle::new(inner, 4));
+    assert_eq!(reader.buffer(), [1, 2]);
+
+    assert_eq!(reader.peek_exact(4).await.unwrap(), [1, 2, 5, 6]);
+    reader.consume(3);
+    assert_eq!(reader.retained(), 3);
+    reader.unconsume(2);
+    assert_eq!(reader.buffer(), [2, 5, 6, 7, 0]);
+
+    reader.consume(4);
+    reader.unread(&[9, 9]).unwrap();
+    assert_eq!(reader.buffer(), [9, 9, 0]);
+
+    // The retained data is dropped once the buffer is refilled
+    assert_eq!(reader.peek(4).await.unwrap(), [9, 9, 0, 1]);
+    assert_eq!(reader.retained(), 0);
+
+    // Passthrough reads return the data first
+    reader.passthrough(true);
+    reader.unread(&[8]).unwrap();
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, [8, 9, 9, 0, 1, 2, 3, 4]);
+}
+
+#[tokio::test]
+async fn test_buf_reader_lines() {
+    let inner: &[u8] = b"GET / HTTP/1.1\r\nHost: caido.io\n\r\nbody";
+    let mut reader = AsyncBufReader::with_chunk_size(4, Trickle::new(inner, 3));
+
+    let lines: Vec<_> = reader.lines(64).map(Result::unwrap).collect().await;
+    assert_eq!(lines, ["GET / HTTP/1.1", "Host: caido.io", "", "body"]);
+
+    let inner: &[u8] = b"a\r\nb\nc\r\n";
+    let mut reader = AsyncBufReader::new(inner);
+    let mut lines = reader.lines(64).with_line_ending(LineEnding::Crlf);
+    assert_eq!(lines.next().await.unwrap().unwrap(), "a");
+    assert_eq!(lines.next().await.unwrap().unwrap(), "b\nc");
+    assert!(lines.next().await.is_none());
+
+    let mut reader: &[u8] = b"a\r\nb";
+    let mut lines = reader.lines(64).with_line_ending(LineEnding::Lf);
+    assert_eq!(lines.next().await.unwrap().unwrap(), "a\r");
+    assert_eq!(lines.next().await.unwrap().unwrap(), "b");
+    assert!(lines.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_buf_reader_lines_passthrough() {
+    let inner: &[u8] = b"a\nbc\n";
+    let mut reader = AsyncBufReader::with_chunk_size(4, inner);
+    let mut lines = AsyncBufReadExt::lines(&mut reader, 64);
+    assert_eq!(lines.next().await.unwrap().unwrap(), "a");
+
+    // The buffered data is not a final segment, the stream is still open
+    reader.passthrough(true);
+    let mut lines = AsyncBufReadExt::lines(&mut reader, 64);
+    let err = lines.next().await.unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert_eq!(reader.buffer(), b"bc");
+
+    reader.passthrough(false);
+    let mut lines = AsyncBufReadExt::lines(&mut reader, 64);
+    assert_eq!(lines.next().await.unwrap().unwrap(), "bc");
+    assert!(lines.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_buf_reader_split() {
+    let inner: &[u8] = &[5, 0, 6, 7, 0, 0, 1, 2, 3, 4, 0];
+    let mut reader = AsyncBufReader::with_chunk_size(2, Trickle::new(inner, 2));
+
+    let mut segments = AsyncBufReadExt::split(&mut reader, 0, 4);
+    assert_eq!(segments.next().await.unwrap().unwrap(), [5].as_slice());
+    assert_eq!(segments.next().await.unwrap().unwrap(), [6, 7].as_slice());
+    assert_eq!(segments.next().await.unwrap().unwrap(), [].as_slice());
+
+    let err = segments.next().await.unwrap().unwrap_err();
+    let inner_err = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(inner_err, Some(&LimitExceeded { limit: 4 }));
+    assert_eq!(reader.buffer(), [1, 2, 3, 4]);
+
+    // Cancelling the stream doesn't lose any data
+    let mut segments = AsyncBufReadExt::split(&mut reader, 0, 8);
+    assert_eq!(
+        segments.next().await.unwrap().unwrap(),
+        [1, 2, 3, 4].as_slice()
+    );
+    assert!(segments.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_buf_reader_take() {
+    let inner = Trickle::new(b"hello world", 2);
+    let mut reader = AsyncBufReader::with_chunk_size(4, inner);
+
+    let mut take = AsyncBufReadExt::take(&mut reader, 5);
+    assert_eq!(take.peek_exact(3).await.unwrap(), b"hel");
+    assert!(!take.ended());
+    assert_eq!(take.peek_exact(5).await.unwrap(), b"hello");
+    assert_eq!(take.peek(8).await.unwrap(), b"hello");
+    assert!(take.ended());
+    let err = take.peek_exact(6).await.unwrap_err();
+    let err = err.get_ref().unwrap().downcast_ref::<UnexpectedEof>();
+    assert_eq!(
+        err,
+        Some(&UnexpectedEof {
+            requested: 6,
+            partial: 5
+        })
+    );
+    let mut buf = Vec::new();
+    take.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"hello");
+    assert_eq!(take.limit(), 0);
+
+    // The reader can be used again after the limit
+    assert_eq!(reader.peek_exact(6).await.unwrap(), b" world");
+
+    // In passthrough mode the limit still applies to direct reads
+    let inner: &[u8] = b"hello world";
+    let mut reader = AsyncBufReader::new(inner);
+    reader.passthrough(true);
+    let mut take = AsyncBufReadExt::take(&mut reader, 7);
+    let mut buf = Vec::new();
+    take.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"hello w");
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"orld");
+}
+
+#[tokio::test]
+async fn test_buf_reader_chain() {
+    let first: &[u8] = b"GET /new HTTP/1.1\r\n";
+    let second = AsyncBufReader::with_chunk_size(4, Trickle::new(b"Host: a\r\n\r\nbody", 3));
+    let mut chain = AsyncBufReadExt::chain(first, second);
+
+    assert_eq!(chain.peek_exact(17).await.unwrap(), b"GET /new HTTP/1.1");
+    chain.consume(17);
+
+    // The request spans both readers
+    assert_eq!(chain.peek_exact(8).await.unwrap(), b"\r\nHost: ");
+    assert!(!chain.ended());
+    assert_eq!(chain.read_bytes(11).await.unwrap(), "\r\nHost: a\r\n");
+
+    let mut buf = Vec::new();
+    chain.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"\r\nbody");
+    assert_eq!(chain.peek(1).await.unwrap(), b"");
+    assert!(chain.ended());
+
+    // Passthrough is forwarded to the readers
+    let first = AsyncBufReader::new(&b"ab"[..]);
+    let second = AsyncBufReader::with_chunk_size(1, &b"cdef"[..]);
+    let mut chain = AsyncBufReadExt::chain(first, second);
+    assert_eq!(chain.peek_exact(3).await.unwrap(), b"abc");
+    chain.consume(3);
+    chain.passthrough(true);
+    assert_eq!(chain.peek(3).await.unwrap(), b"");
+    let mut buf = Vec::new();
+    chain.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"def");
+
+    // It still applies to the second reader once the first one ends
+    let first = AsyncBufReader::new(&b"ab"[..]);
+    let second = AsyncBufReader::with_chunk_size(8, &b"cdef"[..]);
+    let mut chain = AsyncBufReadExt::chain(first, second);
+    chain.passthrough(true);
+    let mut buf = [0; 2];
+    chain.read_exact(&mut buf).await.unwrap();
+    let mut buf = [0; 1];
+    chain.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, *b"c");
+    assert_eq!(chain.get_ref().1.buffer(), b"");
+}
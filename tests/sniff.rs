@@ -0,0 +1,175 @@
+use std::io;
+
+use async_buf_read::sniff::{self, ClientHello, HttpRequest, Protocol, SniffError, TlsVersion};
+use async_buf_read::{AsyncBufReadExt, AsyncBufReader};
+
+use self::common::Trickle;
+
+mod common;
+
+fn client_hello(server_name: &str) -> Vec<u8> {
+    let mut sni = vec![0x00];
+    sni.extend((server_name.len() as u16).to_be_bytes());
+    sni.extend(server_name.as_bytes());
+    let mut sni_list = (sni.len() as u16).to_be_bytes().to_vec();
+    sni_list.extend(sni);
+
+    let mut extensions = vec![0x00, 0x00];
+    extensions.extend((sni_list.len() as u16).to_be_bytes());
+    extensions.extend(sni_list);
+    // supported_versions: GREASE, TLS 1.3, TLS 1.2
+    extensions.extend([
+        0x00, 0x2b, 0x00, 0x07, 0x06, 0x3a, 0x3a, 0x03, 0x04, 0x03, 0x03,
+    ]);
+
+    let mut hello = vec![0x03, 0x03];
+    hello.extend([0; 32]);
+    hello.push(0);
+    hello.extend([0x00, 0x02, 0x13, 0x01]);
+    hello.extend([0x01, 0x00]);
+    hello.extend((extensions.len() as u16).to_be_bytes());
+    hello.extend(extensions);
+
+    let mut handshake = vec![0x01];
+    handshake.extend(&(hello.len() as u32).to_be_bytes()[1..]);
+    handshake.extend(hello);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend((handshake.len() as u16).to_be_bytes());
+    record.extend(handshake);
+    record
+}
+
+#[test]
+fn test_detect() {
+    let tls = client_hello("caido.io");
+    assert_eq!(
+        sniff::detect(&tls),
+        Ok(Protocol::Tls(ClientHello {
+            version: TlsVersion::Tls13,
+            server_name: Some("caido.io".to_string()),
+        }))
+    );
+
+    let request = HttpRequest {
+        method: "GET".to_string(),
+        target: "/chat".to_string(),
+        minor_version: 1,
+    };
+    assert_eq!(
+        sniff::detect(b"GET /chat HTTP/1.1\r\nHost: caido.io\r\n\r\n"),
+        Ok(Protocol::Http1(request.clone()))
+    );
+    assert_eq!(
+        sniff::detect(b"GET /chat HTTP/1.1\r\nConnection: upgrade\r\nUpgrade: WebSocket\r\n\r\n"),
+        Ok(Protocol::WebSocket(request))
+    );
+
+    assert_eq!(
+        sniff::detect(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"),
+        Ok(Protocol::Http2)
+    );
+    assert_eq!(
+        sniff::detect(b"PROXY TCP4 127.0.0.1 127.0.0.1 1234 80\r\n"),
+        Ok(Protocol::ProxyV1)
+    );
+    assert_eq!(
+        sniff::detect(b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c"),
+        Ok(Protocol::ProxyV2)
+    );
+    assert_eq!(
+        sniff::detect(&[0x04, 0x01, 0x00, 0x50, 127, 0, 0, 1, 0x00]),
+        Ok(Protocol::Socks4)
+    );
+    assert_eq!(sniff::detect(&[0x05, 0x01, 0x00]), Ok(Protocol::Socks5));
+}
+
+#[test]
+fn test_detect_partial() {
+    // A ClientHello fragmented over two records
+    let mut tls = client_hello("caido.io");
+    tls[3..5].copy_from_slice(&40u16.to_be_bytes());
+    assert_eq!(
+        sniff::detect(&tls[..45]),
+        Ok(Protocol::Tls(ClientHello {
+            version: TlsVersion::Tls12,
+            server_name: None,
+        }))
+    );
+
+    // Unknown versions are compared on the wire
+    let mut tls = client_hello("caido.io");
+    let grease = tls.windows(2).position(|w| w == [0x3a, 0x3a]).unwrap();
+    tls[grease..grease + 2].copy_from_slice(&[0x02, 0x00]);
+    assert!(matches!(
+        sniff::detect(&tls),
+        Ok(Protocol::Tls(ClientHello {
+            version: TlsVersion::Tls13,
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn test_detect_errors() {
+    assert_eq!(sniff::detect(b""), Err(SniffError::Ambiguous));
+    assert_eq!(sniff::detect(b"P"), Err(SniffError::Ambiguous));
+    assert_eq!(sniff::detect(b"PRI * HTTP/2"), Err(SniffError::Incomplete));
+    assert_eq!(sniff::detect(b"GET / HTTP/1."), Err(SniffError::Incomplete));
+    assert_eq!(
+        sniff::detect(b"GET / HTTP/1.1\r\nUpgrade: websocket\r\n"),
+        Err(SniffError::Incomplete)
+    );
+    assert_eq!(
+        sniff::detect(&[0x05, 0x02, 0x00]),
+        Err(SniffError::Incomplete)
+    );
+    assert_eq!(
+        sniff::detect(&client_hello("caido.io")[..40]),
+        Err(SniffError::Incomplete)
+    );
+    assert_eq!(
+        sniff::detect(b"GET / HTTP/2.0\r\n\r\n"),
+        Err(SniffError::Unrecognized)
+    );
+    assert_eq!(sniff::detect(&[0x00, 0x01]), Err(SniffError::Unrecognized));
+}
+
+#[tokio::test]
+async fn test_sniff() {
+    let tls = client_hello("caido.io");
+    let mut reader = AsyncBufReader::new(Trickle::new(&tls, 7));
+
+    let protocol = sniff::sniff(&mut reader, 1024).await.unwrap();
+    assert!(
+        matches!(protocol, Protocol::Tls(hello) if hello.server_name.as_deref() == Some("caido.io"))
+    );
+    assert_eq!(reader.buffer(), tls);
+
+    let inner: &[u8] = b"GET / HTTP/1.1\r\nHost: caido.io\r\n";
+    let mut reader = AsyncBufReader::new(Trickle::new(inner, 4));
+
+    let err = sniff::sniff(&mut reader, 12).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let inner_err = err.get_ref().unwrap().downcast_ref::<SniffError>();
+    assert_eq!(inner_err, Some(&SniffError::Incomplete));
+    assert_eq!(reader.buffer(), b"GET / HTTP/1");
+
+    let err = sniff::sniff(&mut reader, 1024).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(reader.buffer(), inner);
+    assert!(reader.ended());
+
+    // The request line and the headers arrive in separate reads
+    let inner: &[u8] = b"GET /chat HTTP/1.1\r\nUpgrade: websocket\r\n\r\n";
+    let mut reader = AsyncBufReader::new(Trickle::new(inner, 20));
+    let protocol = sniff::sniff(&mut reader, 1024).await.unwrap();
+    assert!(matches!(protocol, Protocol::WebSocket(_)));
+    assert_eq!(reader.buffer(), inner);
+
+    let mut reader: &[u8] = b"P";
+    let err = sniff::sniff(&mut reader, 1024).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let inner_err = err.get_ref().unwrap().downcast_ref::<SniffError>();
+    assert_eq!(inner_err, Some(&SniffError::Ambiguous));
+}
@@ -0,0 +1,136 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_buf_read::tee::{Backpressure, Tee, TeeSink, WriteSink};
+use async_buf_read::{AsyncBufPassthrough, AsyncBufReadExt, AsyncBufReader};
+use bytes::BytesMut;
+use futures::poll;
+use tokio::pin;
+
+use self::common::{AsyncReadExt, Recorder, Trickle};
+
+mod common;
+
+/// A sink that only accepts data when it is open.
+#[derive(Default)]
+struct Gate {
+    open: bool,
+    // The number of bytes accepted before the gate closes, if limited
+    budget: Option<usize>,
+    data: Vec<u8>,
+}
+
+impl TeeSink for Gate {
+    fn poll_record(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = self.get_mut();
+        let n = me.budget.map_or(buf.len(), |budget| budget.min(buf.len()));
+        if !me.open || n == 0 {
+            return Poll::Pending;
+        }
+        if let Some(budget) = &mut me.budget {
+            *budget -= n;
+        }
+        me.data.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_tee_record() {
+    let reader = AsyncBufReader::with_chunk_size(4, Trickle::new(b"hello world!", 3));
+    let mut tee = Tee::new(reader, BytesMut::new());
+
+    // Peeked data is not recorded
+    assert_eq!(tee.peek_exact(5).await.unwrap(), b"hello");
+    assert_eq!(tee.sink(), "");
+    tee.consume(2);
+    assert_eq!(tee.sink(), "he");
+    assert_eq!(tee.read_bytes(3).await.unwrap(), "llo");
+    assert_eq!(tee.sink(), "hello");
+
+    // Data read in passthrough mode is recorded too
+    tee.passthrough(true);
+    let mut buf = Vec::new();
+    tee.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b" world!");
+    assert_eq!(tee.sink(), "hello world!");
+
+    // Any `AsyncWrite` can be used as a sink
+    let reader = AsyncBufReader::new(&b"hello"[..]);
+    let mut tee = Tee::new(reader, WriteSink::new(Recorder::new(b"", false)));
+    let mut buf = Vec::new();
+    tee.read_to_end(&mut buf).await.unwrap();
+    std::future::poll_fn(|cx| Pin::new(&mut tee).poll_flush(cx))
+        .await
+        .unwrap();
+    let (_, sink) = tee.into_parts();
+    assert_eq!(sink.get_ref().written(), b"hello");
+    assert!(sink.get_ref().flushed);
+}
+
+#[tokio::test]
+async fn test_tee_backpressure() {
+    let reader = AsyncBufReader::new(&b"hello world"[..]);
+    let mut tee = Tee::new(reader, Gate::default());
+    assert_eq!(tee.peek_exact(5).await.unwrap(), b"hello");
+    tee.consume(2);
+
+    // Reading waits for the sink
+    {
+        let peek = tee.peek(5);
+        pin!(peek);
+        assert!(poll!(peek).is_pending());
+    }
+    tee.sink_mut().open = true;
+    assert_eq!(tee.peek(3).await.unwrap(), b"llo");
+    assert_eq!(tee.sink().data, b"he");
+
+    let reader = AsyncBufReader::new(&b"hello world"[..]);
+    let mut tee = Tee::new(reader, Gate::default()).with_backpressure(Backpressure::Drop);
+    assert_eq!(tee.peek_exact(5).await.unwrap(), b"hello");
+    tee.consume(2);
+    assert_eq!(tee.dropped(), 0);
+
+    // The data is dropped once the next read doesn't wait for the sink
+    assert_eq!(tee.peek_exact(3).await.unwrap(), b"llo");
+    assert_eq!(tee.dropped(), 2);
+    tee.sink_mut().open = true;
+    tee.consume(3);
+    assert_eq!(tee.sink().data, b"llo");
+}
+
+#[tokio::test]
+async fn test_tee_backpressure_drop_partial() {
+    let reader = AsyncBufReader::new(&b"hello world"[..]);
+    let sink = Gate {
+        open: true,
+        budget: Some(3),
+        data: Vec::new(),
+    };
+    let mut tee = Tee::new(reader, sink).with_backpressure(Backpressure::Drop);
+
+    // The rest of a partially accepted chunk is kept
+    assert_eq!(tee.peek_exact(5).await.unwrap(), b"hello");
+    tee.consume(5);
+    assert_eq!(tee.sink().data, b"hel");
+    assert_eq!(tee.peek_exact(1).await.unwrap(), b" ");
+    assert_eq!(tee.dropped(), 0);
+
+    // The chunks that were not started are dropped
+    tee.consume(1);
+    assert_eq!(tee.peek_exact(2).await.unwrap(), b"wo");
+    assert_eq!(tee.dropped(), 1);
+
+    tee.sink_mut().budget = None;
+    tee.consume(2);
+    assert_eq!(tee.sink().data, b"hellowo");
+}
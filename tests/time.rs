@@ -0,0 +1,126 @@
+#![cfg(feature = "time")]
+
+use std::io;
+use std::time::Duration;
+
+use async_buf_read::time::{Deadline, TimedOut};
+use async_buf_read::{AsyncBufPassthrough, AsyncBufReadExt, AsyncBufReader, Throttled};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, duplex};
+use tokio::time::{Instant, sleep};
+
+use self::common::Trickle;
+
+mod common;
+
+fn timed_out(err: io::Error) -> TimedOut {
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    *err.get_ref().unwrap().downcast_ref::<TimedOut>().unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_peek_timeout() {
+    let (client, mut server) = duplex(64);
+    let mut reader = AsyncBufReader::new(client);
+    server.write_all(b"hel").await.unwrap();
+
+    let err = reader
+        .peek_exact_timeout(5, Duration::from_secs(1))
+        .await
+        .unwrap_err();
+    assert_eq!(timed_out(err).timeout, Duration::from_secs(1));
+    assert_eq!(reader.buffer(), b"hel");
+
+    server.write_all(b"lo\n").await.unwrap();
+    let timeout = Duration::from_secs(1);
+    assert_eq!(
+        reader.peek_until_timeout(b"\n", 8, timeout).await.unwrap(),
+        b"hello\n"
+    );
+    assert_eq!(
+        reader.read_bytes_timeout(5, timeout).await.unwrap(),
+        "hello"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_deadline() {
+    let (client, mut server) = duplex(64);
+    let mut reader = Deadline::new(AsyncBufReader::new(client))
+        .with_idle_timeout(Duration::from_secs(1))
+        .with_total_timeout(Duration::from_secs(3));
+
+    // New data resets the idle timer
+    server.write_all(b"he").await.unwrap();
+    assert_eq!(reader.peek_exact(2).await.unwrap(), b"he");
+    sleep(Duration::from_millis(800)).await;
+    server.write_all(b"l").await.unwrap();
+    assert_eq!(reader.peek_exact(3).await.unwrap(), b"hel");
+
+    // The buffered data is kept after a timeout
+    let err = reader.peek_exact(4).await.unwrap_err();
+    assert_eq!(timed_out(err).timeout, Duration::from_secs(1));
+    assert_eq!(reader.peek(3).await.unwrap(), b"hel");
+
+    // A peer sending data slowly hits the total timeout
+    reader.reset();
+    tokio::spawn(async move {
+        loop {
+            sleep(Duration::from_millis(600)).await;
+            if server.write_all(b"l").await.is_err() {
+                break;
+            }
+        }
+    });
+    let err = reader.peek_exact(64).await.unwrap_err();
+    assert_eq!(timed_out(err).timeout, Duration::from_secs(3));
+    assert!(reader.get_ref().buffer().len() > 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_throttled() {
+    let data = [b'a'; 40];
+    let reader = AsyncBufReader::new(Trickle::new(&data, 4));
+    let mut reader = Throttled::new(reader, 10).with_burst(10);
+
+    // The burst is available right away, then 10 bytes per second
+    let start = Instant::now();
+    assert_eq!(reader.peek_exact(10).await.unwrap().len(), 10);
+    assert!(start.elapsed() < Duration::from_millis(100));
+    assert_eq!(reader.peek_exact(30).await.unwrap().len(), 30);
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(1500), "{elapsed:?}");
+    assert!(elapsed <= Duration::from_secs(3), "{elapsed:?}");
+
+    // Buffered data is not throttled
+    reader.set_rate(1);
+    let start = Instant::now();
+    assert_eq!(reader.peek_exact(30).await.unwrap().len(), 30);
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    reader.consume(30);
+
+    // Reads in passthrough mode are throttled too
+    reader.set_rate(5);
+    reader.passthrough(true);
+    let start = Instant::now();
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, [b'a'; 10]);
+    assert!(start.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_throttled_peek_then_read() {
+    let data = [b'a'; 40];
+    let reader = AsyncBufReader::new(Trickle::new(&data, 4));
+    let mut reader = Throttled::new(reader, 10).with_burst(10);
+
+    // The peeked data is not paid for again when it is read
+    let start = Instant::now();
+    assert_eq!(reader.peek_exact(10).await.unwrap().len(), 10);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, data);
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(2500), "{elapsed:?}");
+    assert!(elapsed < Duration::from_millis(3500), "{elapsed:?}");
+}
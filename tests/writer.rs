@@ -0,0 +1,97 @@
+use async_buf_read::{AsyncBufPassthrough, AsyncBufReadExt, AsyncBufStream, AsyncBufWriter};
+
+use self::common::{AsyncReadExt, AsyncWriteExt, Recorder};
+
+mod common;
+
+#[tokio::test]
+async fn test_buf_writer_coalesce() {
+    let mut writer = AsyncBufWriter::with_chunk_size(8, Recorder::new(&[], true));
+
+    writer.write_all(&[5, 6, 7]).await.unwrap();
+    writer.write_all(&[0, 1]).await.unwrap();
+    assert_eq!(writer.buffer(), [5, 6, 7, 0, 1]);
+    assert!(writer.get_ref().writes.is_empty());
+
+    // Going over the threshold writes everything in a single vectored write
+    writer.write_all(&[2, 3, 4]).await.unwrap();
+    assert_eq!(writer.buffer(), []);
+    assert_eq!(writer.get_ref().writes, [vec![5, 6, 7, 0, 1, 2, 3, 4]]);
+
+    writer.write_all(&[11, 10]).await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(writer.buffer(), []);
+    assert_eq!(
+        writer.get_ref().writes,
+        [vec![5, 6, 7, 0, 1, 2, 3, 4], vec![11, 10]]
+    );
+    assert!(writer.get_ref().flushed);
+}
+
+#[tokio::test]
+async fn test_buf_writer_not_vectored() {
+    let mut writer = AsyncBufWriter::with_chunk_size(4, Recorder::new(&[], false));
+
+    writer.write_all(&[5, 6, 7]).await.unwrap();
+    writer.write_all(&[0, 1, 2, 3, 4]).await.unwrap();
+    assert_eq!(writer.buffer(), []);
+    assert_eq!(
+        writer.get_ref().writes,
+        [vec![5, 6, 7], vec![0, 1, 2, 3, 4]]
+    );
+}
+
+#[tokio::test]
+async fn test_buf_writer_flush_threshold() {
+    let mut writer =
+        AsyncBufWriter::with_chunk_size(4, Recorder::new(&[], true)).with_flush_threshold(16);
+    assert_eq!(writer.flush_threshold(), 16);
+
+    writer.write_all(&[5, 6, 7, 0, 1, 2, 3, 4]).await.unwrap();
+    assert_eq!(writer.buffer(), [5, 6, 7, 0, 1, 2, 3, 4]);
+    assert!(writer.get_ref().writes.is_empty());
+}
+
+#[tokio::test]
+async fn test_buf_writer_passthrough() {
+    let mut writer = AsyncBufWriter::with_chunk_size(8, Recorder::new(&[], false));
+
+    writer.write_all(&[5, 6, 7]).await.unwrap();
+    assert_eq!(writer.buffer(), [5, 6, 7]);
+
+    writer.passthrough(true);
+
+    // The buffer is written first, then the data goes straight through
+    writer.write_all(&[0, 1]).await.unwrap();
+    writer.write_all(&[2]).await.unwrap();
+    assert_eq!(writer.buffer(), []);
+    assert_eq!(
+        writer.get_ref().writes,
+        [vec![5, 6, 7], vec![0, 1], vec![2]]
+    );
+}
+
+#[tokio::test]
+async fn test_buf_stream() {
+    let inner: &[u8] = &[5, 6, 7, 0, 1, 2, 3, 4, 11, 10];
+    let mut stream = AsyncBufStream::with_chunk_sizes(4, 8, Recorder::new(inner, true));
+
+    let buf = stream.peek(3).await.unwrap();
+    assert_eq!(buf, [5, 6, 7]);
+
+    stream.write_all(&[1, 2, 3]).await.unwrap();
+    assert!(stream.get_ref().writes.is_empty());
+    stream.flush().await.unwrap();
+    assert_eq!(stream.get_ref().written(), [1, 2, 3]);
+
+    stream.passthrough(true);
+
+    let mut buf = [0; 16];
+    let nread = stream.read(&mut buf).await.unwrap();
+    assert_eq!(buf[..nread], [5, 6, 7, 0]);
+    let nread = stream.read(&mut buf).await.unwrap();
+    assert_eq!(buf[..nread], [1, 2, 3, 4, 11, 10]);
+
+    stream.write_all(&[4]).await.unwrap();
+    assert_eq!(stream.get_ref().writes, [vec![1, 2, 3], vec![4]]);
+}